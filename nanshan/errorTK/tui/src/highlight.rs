@@ -0,0 +1,173 @@
+// 围栏代码块的语法高亮：只影响详情面板的只读展示。flat_lines / Visual 选区
+// 仍然使用未高亮的纯文本（见 main.rs 的 rebuild_flat_lines），因此 yank 得到的
+// 依旧是原始字符，不会把 ratatui 的样式信息混进剪贴板。
+
+use std::sync::OnceLock;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme as SynTheme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::theme::ThemeKind;
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static SET: OnceLock<ThemeSet> = OnceLock::new();
+    SET.get_or_init(ThemeSet::load_defaults)
+}
+
+fn syntect_theme(kind: ThemeKind) -> &'static SynTheme {
+    let ts = theme_set();
+    let name = match kind {
+        ThemeKind::Dark | ThemeKind::HighContrast => "base16-ocean.dark",
+        ThemeKind::Light => "InspiredGitHub",
+    };
+    &ts.themes[name]
+}
+
+/// 将可能包含 ```lang ... ``` 围栏代码块的文本渲染成 `Line` 列表：围栏内按
+/// `lang` 对应的语法逐 token 上色，找不到对应语法时退化为纯文本高亮；围栏外
+/// 的文本整体使用 `default_fg`，并额外做一遍轻量 Markdown 强调（`**粗体**`、
+/// `` `行内代码` ``），用 `accent` 给行内代码和加粗文本上色。
+pub fn highlight_text(
+    text: &str,
+    theme_kind: ThemeKind,
+    default_fg: Color,
+    accent: Color,
+) -> Vec<Line<'static>> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    loop {
+        match find_fence(rest) {
+            Some((before, lang, code, after)) => {
+                push_plain_lines(&mut out, before, default_fg, accent);
+                out.extend(highlight_code_block(lang, code, theme_kind));
+                rest = after;
+            }
+            None => {
+                push_plain_lines(&mut out, rest, default_fg, accent);
+                break;
+            }
+        }
+    }
+    out
+}
+
+fn push_plain_lines(out: &mut Vec<Line<'static>>, text: &str, fg: Color, accent: Color) {
+    for line in text.lines() {
+        out.push(Line::from(emphasize_line(line, fg, accent)));
+    }
+}
+
+/// 在一行纯文本里找 `**粗体**` 与 `` `行内代码` ``，其余部分保持 `fg`；
+/// 标记不闭合时整行按原样降级为普通文本，不尝试跨行匹配。
+fn emphasize_line(line: &str, fg: Color, accent: Color) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        let bold_pos = rest.find("**");
+        let code_pos = rest.find('`');
+        let use_bold = match (bold_pos, code_pos) {
+            (Some(b), Some(c)) => b <= c,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => {
+                spans.push(Span::styled(rest.to_string(), Style::default().fg(fg)));
+                break;
+            }
+        };
+        if use_bold {
+            let b = bold_pos.unwrap();
+            match rest[b + 2..].find("**") {
+                Some(end_rel) => {
+                    if b > 0 {
+                        spans.push(Span::styled(rest[..b].to_string(), Style::default().fg(fg)));
+                    }
+                    let inner = &rest[b + 2..b + 2 + end_rel];
+                    spans.push(Span::styled(
+                        inner.to_string(),
+                        Style::default().fg(fg).add_modifier(Modifier::BOLD),
+                    ));
+                    rest = &rest[b + 2 + end_rel + 2..];
+                }
+                None => {
+                    spans.push(Span::styled(rest.to_string(), Style::default().fg(fg)));
+                    break;
+                }
+            }
+        } else {
+            let c = code_pos.unwrap();
+            match rest[c + 1..].find('`') {
+                Some(end_rel) => {
+                    if c > 0 {
+                        spans.push(Span::styled(rest[..c].to_string(), Style::default().fg(fg)));
+                    }
+                    let inner = &rest[c + 1..c + 1 + end_rel];
+                    spans.push(Span::styled(inner.to_string(), Style::default().fg(accent)));
+                    rest = &rest[c + 1 + end_rel + 1..];
+                }
+                None => {
+                    spans.push(Span::styled(rest.to_string(), Style::default().fg(fg)));
+                    break;
+                }
+            }
+        }
+    }
+    if spans.is_empty() {
+        spans.push(Span::styled(String::new(), Style::default().fg(fg)));
+    }
+    spans
+}
+
+/// 定位下一对围栏，返回 (围栏前文本, 语言标记, 代码体, 围栏后文本)；
+/// 没有闭合围栏时视为没有代码块，交由调用方按纯文本处理。
+fn find_fence(text: &str) -> Option<(&str, &str, &str, &str)> {
+    let start = text.find("```")?;
+    let before = &text[..start];
+    let after_open = &text[start + 3..];
+    let line_end = after_open.find('\n').unwrap_or(after_open.len());
+    let lang = after_open[..line_end].trim();
+    let body_start = (line_end + 1).min(after_open.len());
+    let body = &after_open[body_start..];
+    let close = body.find("```")?;
+    let code = &body[..close];
+    let after = &body[close + 3..];
+    Some((before, lang, code, after))
+}
+
+fn highlight_code_block(lang: &str, code: &str, theme_kind: ThemeKind) -> Vec<Line<'static>> {
+    let ss = syntax_set();
+    let syntax = ss
+        .find_syntax_by_token(lang)
+        .or_else(|| ss.find_syntax_by_extension(lang))
+        .unwrap_or_else(|| ss.find_syntax_plain_text());
+    let mut h = HighlightLines::new(syntax, syntect_theme(theme_kind));
+    let mut out = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let trimmed = line.trim_end_matches('\n');
+        let spans = match h.highlight_line(line, ss) {
+            Ok(ranges) => ranges
+                .into_iter()
+                .map(|(style, s)| {
+                    let fg = style.foreground;
+                    Span::styled(
+                        s.trim_end_matches('\n').to_string(),
+                        Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b)),
+                    )
+                })
+                .collect(),
+            Err(_) => vec![Span::raw(trimmed.to_string())],
+        };
+        out.push(Line::from(spans));
+    }
+    out
+}
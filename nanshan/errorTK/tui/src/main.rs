@@ -10,7 +10,9 @@ use std::{
     fs, io,
     path::{Path, PathBuf},
     process::Command,
-    time::Duration,
+    sync::{mpsc, OnceLock},
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
@@ -19,13 +21,14 @@ use clap::{ArgAction, Parser, ValueEnum};
 use crossterm::{
     event::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
@@ -34,12 +37,31 @@ use ratatui::{
     },
     Frame, Terminal,
 };
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use tui_textarea::{CursorMove, Scrolling, TextArea};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+mod highlight;
+mod query;
+mod semantic;
+mod store;
+mod template;
+mod theme;
+use highlight::highlight_text;
+use semantic::{EmbeddingClient, EmbeddingIndex};
+use store::{JsonStore, SqliteStore, Store};
+use template::{
+    load_templates, FlashNoteContext, FlashQuestionContext, ListRowContext, Templates, COLOR_END,
+    COLOR_SEP, COLOR_START,
+};
+use theme::{
+    apply_no_color, load_theme_registry, no_color_requested, theme_kind_from_name,
+    theme_kind_name, Theme, ThemeKind,
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 enum SourceKind {
     Simulation,
@@ -84,9 +106,39 @@ struct Cli {
     #[arg(long = "daily-limit", default_value_t = 0)]
     daily_limit: usize,
 
-    /// 主题（外观）：dark | light
+    /// 主题（外观）：dark | light | high-contrast，运行时可用 [T] 循环切换，
+    /// 也可以在 errorTK/tui/themes/*.toml 里追加自定义主题
     #[arg(long = "theme", value_enum, default_value_t = ThemeKind::Dark)]
     theme: ThemeKind,
+
+    /// 强制无色模式（等价于设置 NO_COLOR）：所有颜色收敛成终端默认色，
+    /// 选区/光标改用反显、粗体等 Modifier 表达，适合哑终端或录屏场景
+    #[arg(long = "no-color", action = ArgAction::SetTrue)]
+    no_color: bool,
+
+    /// 使用 SQLite 数据库作为存储后端（替代 errors.json 整文件重写）
+    #[arg(long = "db")]
+    db: Option<PathBuf>,
+
+    /// 一次性将 --file 指向的 errors.json 导入 --db 指定的数据库，导入后直接退出
+    #[arg(long = "import-json", action = ArgAction::SetTrue)]
+    import_json: bool,
+
+    /// 启动时即开启语义检索模式（也可在界面内用快捷键切换）
+    #[arg(long = "semantic", action = ArgAction::SetTrue)]
+    semantic: bool,
+
+    /// OpenAI 兼容的 /embeddings 接口地址，语义检索依赖它取得向量
+    #[arg(long = "embeddings-endpoint")]
+    embeddings_endpoint: Option<String>,
+
+    /// 访问 embeddings 接口的 API Key（可选）
+    #[arg(long = "embeddings-key")]
+    embeddings_key: Option<String>,
+
+    /// embeddings 模型名
+    #[arg(long = "embeddings-model", default_value = "text-embedding-3-small")]
+    embeddings_model: String,
 }
 
 // ---------------- 数据结构 ----------------
@@ -156,6 +208,7 @@ struct RowRef {
 #[derive(Debug)]
 struct App {
     data: ErrorData,
+    store: Box<dyn Store>,
     rows: Vec<RowRef>,
     list_state: ListState,
     show_answer: bool,               // 全局：是否显示答案/解析
@@ -167,7 +220,15 @@ struct App {
     due_only: bool,
     daily_limit: Option<usize>,
     theme: Theme,
-    keymap: HashMap<char, KeyAction>,
+    theme_kind: ThemeKind,
+    theme_registry: Vec<(String, Theme)>,
+    theme_index: usize,
+    keymap: Keymap,
+    // 多键组合（如 `g g`）等待后续按键的缓冲区；超过 `PENDING_OP_TIMEOUT`
+    // 还没等到下一键就视为放弃，见 `keymap_pending_expired`。
+    keymap_pending: Vec<KeyToken>,
+    keymap_pending_since: Option<Instant>,
+    templates: Templates,
     // Visual 模式与笔记
     focus: Focus,
     mode: Mode,
@@ -175,6 +236,10 @@ struct App {
     cursor_col: usize,
     sel_start: Option<(usize, usize)>,
     flat_lines: Vec<String>,
+    // `flat_lines` 每次被 `rebuild_flat_lines` 重写时自增一次，`draw_detail`
+    // 靠比较这个代数是否变化来判断能不能复用上一帧算好的换行缓存。
+    flat_lines_generation: u64,
+    detail_cache: Option<DetailCache>,
     editor: Option<Editor>,
     notes: NotesStore,
     visual_kind: VisualKind,
@@ -191,30 +256,88 @@ struct App {
     filtered_note_indices: Vec<usize>,
     note_indent_levels: Vec<usize>,
     note_fold_mode: NotesFoldMode,
+    // 模糊搜索命中的字符下标（按笔记在 `notes.data.notes` 里的下标做键），
+    // 用于在列表里把命中片段用 `th.accent` 高亮；语义检索模式下不产生命中位置。
+    note_search_highlights: HashMap<usize, (Vec<usize>, Vec<usize>)>,
     question_search_query: Option<String>,
     question_search_active: bool,
     question_filtered_indices: Vec<usize>,
+    // 模糊搜索命中的字符下标（按行在 `rows` 里的下标做键），对应预览首行
+    // （`question_content_preview`）；同 `note_search_highlights`，结构化
+    // 查询/语义检索模式下不产生命中位置。
+    question_search_highlights: HashMap<usize, Vec<usize>>,
     // flashcards
     flash_mode: bool,
     flash_cards: Vec<FlashCardSource>,
     flash_pos: usize,
     flash_revealed: bool,
+    // 语义检索
+    semantic_mode: bool,
+    embeddings_path: PathBuf,
+    embedding_index: EmbeddingIndex,
+    embedding_client: Option<EmbeddingClient>,
+    // embeddings 接口是阻塞 HTTP 调用，题目语义检索放到后台线程跑（见
+    // `spawn_semantic_question_search`/`poll_semantic_job`），这里只持有
+    // 结果通道；`question_search_generation` 在用户开始新一轮搜索或取消时
+    // 递增，后台结果回来时代数对不上就丢弃，避免覆盖用户已经看到的新状态。
+    semantic_job: Option<SemanticJob>,
+    question_search_generation: u64,
+    // 文件监听：errors.json / notes.json 在外部被修改时自动重载；
+    // 使用 --db 时改为监听 db_path，同一个文件的改动同时触发数据和笔记重载。
+    data_path: PathBuf,
+    db_path: Option<PathBuf>,
+    watcher: Option<FileWatcher>,
+    reload_rx: Option<mpsc::Receiver<notify::Result<notify::Event>>>,
+    // 外部变更提示（由文件监听触发）
+    reload_notice: Option<String>,
+    // 鼠标交互：记录上一帧各个可点击控件的 Rect，供事件循环做命中测试
+    list_area: Rect,
+    notes_list_area: Rect,
+    detail_area: Rect,
+    flash_reveal_area: Rect,
+    flash_grade_areas: Vec<(&'static str, Rect)>,
+}
+
+/// 仅用于让 `App` 能继续 `#[derive(Debug)]`；监听器本身无需可打印。
+struct FileWatcher(#[allow(dead_code)] RecommendedWatcher);
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FileWatcher(..)")
+    }
 }
 
 impl App {
     fn new(
         data: ErrorData,
+        store: Box<dyn Store>,
         filter_sources: Vec<SourceKind>,
         show_comments: bool,
         exam_date: Option<chrono::NaiveDate>,
         due_only: bool,
         daily_limit: Option<usize>,
         theme: Theme,
-        keymap: HashMap<char, KeyAction>,
+        theme_kind: ThemeKind,
+        theme_registry: Vec<(String, Theme)>,
+        theme_index: usize,
+        keymap: Keymap,
         notes: NotesStore,
+        semantic_mode: bool,
+        embeddings_path: PathBuf,
+        embedding_client: Option<EmbeddingClient>,
+        data_path: PathBuf,
+        db_path: Option<PathBuf>,
+        reload_watch: Option<(RecommendedWatcher, mpsc::Receiver<notify::Result<notify::Event>>)>,
     ) -> Self {
+        let (watcher, reload_rx) = match reload_watch {
+            Some((w, rx)) => (Some(FileWatcher(w)), Some(rx)),
+            None => (None, None),
+        };
+        let embedding_index = EmbeddingIndex::open(&embeddings_path);
+        let templates = load_templates();
         let mut app = Self {
             data,
+            store,
             rows: vec![],
             list_state: ListState::default(),
             show_answer: false,
@@ -226,13 +349,21 @@ impl App {
             due_only,
             daily_limit,
             theme,
+            theme_kind,
+            theme_registry,
+            theme_index,
             keymap,
+            keymap_pending: Vec::new(),
+            keymap_pending_since: None,
+            templates,
             focus: Focus::List,
             mode: Mode::Normal,
             cursor_line: 0,
             cursor_col: 0,
             sel_start: None,
             flat_lines: vec![],
+            flat_lines_generation: 0,
+            detail_cache: None,
             editor: None,
             notes,
             visual_kind: VisualKind::Char,
@@ -248,13 +379,31 @@ impl App {
             filtered_note_indices: Vec::new(),
             note_indent_levels: Vec::new(),
             note_fold_mode: NotesFoldMode::Full,
+            note_search_highlights: HashMap::new(),
             question_search_query: None,
             question_search_active: false,
             question_filtered_indices: Vec::new(),
+            question_search_highlights: HashMap::new(),
             flash_mode: false,
             flash_cards: Vec::new(),
             flash_pos: 0,
             flash_revealed: false,
+            semantic_mode,
+            embeddings_path,
+            embedding_index,
+            embedding_client,
+            semantic_job: None,
+            question_search_generation: 0,
+            data_path,
+            db_path,
+            watcher,
+            reload_rx,
+            reload_notice: None,
+            list_area: Rect::default(),
+            notes_list_area: Rect::default(),
+            detail_area: Rect::default(),
+            flash_reveal_area: Rect::default(),
+            flash_grade_areas: Vec::new(),
         };
         app.rebuild_rows();
         app.list_state.select(Some(0));
@@ -262,6 +411,17 @@ impl App {
         app
     }
 
+    fn clear_keymap_pending(&mut self) {
+        self.keymap_pending.clear();
+        self.keymap_pending_since = None;
+    }
+
+    fn keymap_pending_expired(&self) -> bool {
+        self.keymap_pending_since
+            .map(|t| t.elapsed() > PENDING_OP_TIMEOUT)
+            .unwrap_or(false)
+    }
+
     fn rebuild_rows(&mut self) {
         self.rows.clear();
         let include = |k: SourceKind, v: &Vec<Question>| -> bool {
@@ -383,6 +543,85 @@ impl App {
     }
 }
 
+// 监听 errors.json / notes.json 所在目录：外部编辑器常以“写临时文件再改名”的方式
+// 保存，直接监听文件本身会在改名后失效，因此监听父目录并按路径过滤事件。
+fn watch_data_dirs(
+    paths: &[PathBuf],
+) -> Result<(RecommendedWatcher, mpsc::Receiver<notify::Result<notify::Event>>)> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })?;
+    for p in paths {
+        if let Some(dir) = p.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+    }
+    Ok((watcher, rx))
+}
+
+fn event_touches_path(event: &notify::Event, path: &Path) -> bool {
+    event.paths.iter().any(|p| {
+        p == path || p.file_name().is_some() && p.file_name() == path.file_name() && p.parent() == path.parent()
+    })
+}
+
+/// 消费文件监听队列里积压的事件，命中 errors.json / notes.json 时就地重载。
+/// 不阻塞：队列为空直接返回，由主循环每帧调用一次。
+fn poll_file_reload(app: &mut App) -> Result<()> {
+    let events: Vec<notify::Event> = match app.reload_rx.as_ref() {
+        Some(rx) => {
+            let mut v = Vec::new();
+            while let Ok(Ok(evt)) = rx.try_recv() {
+                v.push(evt);
+            }
+            v
+        }
+        None => return Ok(()),
+    };
+    if events.is_empty() {
+        return Ok(());
+    }
+    // --db 模式下数据和笔记共用同一个 sqlite 文件，命中 db_path 就两边一起重载；
+    // 否则沿用 errors.json / notes.json 两个独立路径各自判断。
+    let (data_changed, notes_changed) = if let Some(db_path) = app.db_path.clone() {
+        let touched = events.iter().any(|e| event_touches_path(e, &db_path));
+        (touched, touched)
+    } else {
+        let data_changed = events.iter().any(|e| event_touches_path(e, &app.data_path));
+        let notes_changed = events
+            .iter()
+            .any(|e| event_touches_path(e, &app.notes.path));
+        (data_changed, notes_changed)
+    };
+
+    if data_changed {
+        app.data = app.store.load()?;
+        app.rebuild_rows();
+        // 正在跑的后台语义检索拿到的是重载前的行快照，下标对不上新的
+        // app.rows，代数加一让 poll_semantic_job 把它当成过期结果丢掉。
+        app.question_search_generation += 1;
+        app.semantic_job = None;
+    }
+    if notes_changed {
+        app.notes.reload(app.store.as_mut())?;
+        rebuild_note_view(app);
+    }
+    app.reload_notice = match (data_changed, notes_changed, app.db_path.is_some()) {
+        (true, true, true) => Some("检测到外部修改，已重载数据库".into()),
+        (true, true, false) => Some("检测到外部修改，已重载 errors.json 与 notes.json".into()),
+        (true, false, _) => Some("检测到外部修改，已重载 errors.json".into()),
+        (false, true, _) => Some("检测到外部修改，已重载 notes.json".into()),
+        (false, false, _) => app.reload_notice.take(),
+    };
+    Ok(())
+}
+
 fn default_data_path(cli: &Cli) -> PathBuf {
     if let Some(p) = &cli.file {
         return p.clone();
@@ -425,6 +664,24 @@ struct ExamState {
     priority: u8,
     due: Option<String>,
     history: Vec<ReviewEvent>,
+    // SM-2：逐题独立的易度/复习计数/间隔天数
+    #[serde(default = "default_ease_factor")]
+    ease_factor: f64,
+    #[serde(default)]
+    reps: u32,
+    #[serde(default = "default_interval_days")]
+    interval_days: f64,
+}
+
+const MIN_INTERVAL_DAYS: f64 = 10.0 / 1440.0; // 10 分钟，作为考试临界兜底
+const MIN_EASE_FACTOR: f64 = 1.3;
+
+fn default_ease_factor() -> f64 {
+    2.5
+}
+
+fn default_interval_days() -> f64 {
+    1.0
 }
 
 fn default_exam_state() -> ExamState {
@@ -434,43 +691,44 @@ fn default_exam_state() -> ExamState {
         priority: 1,
         due: None,
         history: vec![],
+        ease_factor: default_ease_factor(),
+        reps: 0,
+        interval_days: default_interval_days(),
     }
 }
 
+// SM-2：again/hard/good/easy 映射为质量分 q，据此滚动 ease_factor 与 interval_days。
 fn apply_exam_grade(ex: &mut ExamState, grade: &str, exam_date: Option<chrono::NaiveDate>) {
     let now = Utc::now();
-    let again_seq: [f64; 3] = [10.0 / 1440.0, 4.0 / 24.0, 1.0];
-    let hard_seq: [f64; 5] = [1.0, 3.0, 7.0, 14.0, 28.0];
-    let good_seq: [f64; 4] = [2.0, 5.0, 12.0, 25.0];
-    let easy_seq: [f64; 3] = [4.0, 10.0, 24.0];
-
-    let mut next_days = match grade {
-        "again" => {
-            ex.again_streak = (ex.again_streak.saturating_add(1)).min(3);
-            ex.stage = ex.stage.saturating_sub(1);
-            again_seq[(ex.again_streak as usize - 1).min(again_seq.len() - 1)]
-        }
-        "hard" => {
-            ex.again_streak = 0;
-            let i = (ex.stage as usize).min(hard_seq.len() - 1);
-            ex.stage = ex.stage.saturating_add(1);
-            hard_seq[i]
-        }
-        "good" => {
-            ex.again_streak = 0;
-            let i = (ex.stage as usize).min(good_seq.len() - 1);
-            ex.stage = ex.stage.saturating_add(1);
-            good_seq[i]
-        }
-        "easy" => {
-            ex.again_streak = 0;
-            let i = (ex.stage as usize).min(easy_seq.len() - 1);
-            ex.stage = ex.stage.saturating_add(1);
-            easy_seq[i]
-        }
-        _ => 2.0,
+    let q: f64 = match grade {
+        "again" => 2.0,
+        "hard" => 3.0,
+        "good" => 4.0,
+        "easy" => 5.0,
+        _ => 4.0,
     };
 
+    if q < 3.0 {
+        ex.again_streak = (ex.again_streak.saturating_add(1)).min(3);
+        ex.stage = ex.stage.saturating_sub(1);
+        ex.reps = 0;
+        ex.interval_days = 1.0;
+    } else {
+        ex.again_streak = 0;
+        ex.interval_days = if ex.reps == 0 {
+            1.0
+        } else if ex.reps == 1 {
+            6.0
+        } else {
+            (ex.interval_days * ex.ease_factor).round()
+        };
+        ex.reps = ex.reps.saturating_add(1);
+        ex.stage = ex.stage.saturating_add(1);
+    }
+    ex.ease_factor = (ex.ease_factor + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)))
+        .max(MIN_EASE_FACTOR);
+
+    let mut next_days = ex.interval_days;
     if let Some(ed) = exam_date {
         let rest_days = (ed
             .and_hms_opt(7, 0, 0)
@@ -480,9 +738,9 @@ fn apply_exam_grade(ex: &mut ExamState, grade: &str, exam_date: Option<chrono::N
             .num_seconds() as f64
             / 86400.0;
         if rest_days > 0.0 {
-            next_days = next_days.min((rest_days - 2.0).max(again_seq[0]));
+            next_days = next_days.min((rest_days - 2.0).max(MIN_INTERVAL_DAYS));
         } else {
-            next_days = again_seq[0];
+            next_days = MIN_INTERVAL_DAYS;
         }
     }
 
@@ -559,7 +817,7 @@ fn days_to_duration(days: f64) -> chrono::Duration {
     chrono::Duration::seconds(secs as i64)
 }
 
-fn grade_and_schedule(app: &mut App, data_path: &PathBuf, grade: &str) -> Result<()> {
+fn grade_and_schedule(app: &mut App, grade: &str) -> Result<()> {
     if let Some(idx) = app.list_state.selected() {
         let rr = app.rows[idx].clone();
         let now = Utc::now();
@@ -591,7 +849,8 @@ fn grade_and_schedule(app: &mut App, data_path: &PathBuf, grade: &str) -> Result
             _ => {}
         }
         q.last_reviewed = Some(to_rfc3339(now));
-        save_data(data_path, &app.data)?;
+        let snapshot = q.clone();
+        app.store.save_question(&app.data, rr.src, &snapshot)?;
         // 评分后若仅看到期，需要重建列表以便下一题顶上来
         if app.due_only {
             app.rebuild_rows();
@@ -603,18 +862,48 @@ fn grade_and_schedule(app: &mut App, data_path: &PathBuf, grade: &str) -> Result
 fn main() -> Result<()> {
     let cli = Cli::parse();
     let data_path = default_data_path(&cli);
+
+    // 一次性导入：把 errors.json 灌入 --db 指定的数据库后直接退出，不进入 TUI。
+    if cli.import_json {
+        let db_path = cli
+            .db
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("--import-json 需要同时指定 --db <path>"))?;
+        store::import_json(&db_path, &data_path)?;
+        println!("已将 {} 导入到 {}", data_path.display(), db_path.display());
+        return Ok(());
+    }
+
     let sources = if cli.sources.is_empty() {
         vec![SourceKind::Simulation, SourceKind::Real]
     } else {
         cli.sources.clone()
     };
-    let data = load_data(&data_path)?;
-    let keymap = load_keymap().unwrap_or_else(|_| default_keymap());
     let notes_path = data_path
         .parent()
         .map(|p| p.join("notes.json"))
         .unwrap_or_else(|| PathBuf::from("notes.json"));
-    let notes = NotesStore::open(notes_path)?;
+    let mut store: Box<dyn Store> = match &cli.db {
+        Some(db_path) => Box::new(SqliteStore::open(db_path.clone())?),
+        None => Box::new(JsonStore::new(data_path.clone(), notes_path.clone())),
+    };
+    let data = store.load()?;
+    let keymap = load_keymap().unwrap_or_else(|_| default_keymap());
+    let theme_registry = load_theme_registry();
+    let theme_index = theme_registry
+        .iter()
+        .position(|(name, _)| name == theme_kind_name(cli.theme))
+        .unwrap_or(0);
+    let mono = no_color_requested(cli.no_color);
+    let initial_theme = apply_no_color(theme_registry[theme_index].1, mono);
+    let notes = NotesStore::open(store.as_mut(), notes_path.clone())?;
+    // 监听失败（如文件系统不支持 inotify）不影响正常使用，只是没有自动重载。
+    // --db 模式下真正的数据源是 sqlite 文件，监听它而不是从没被写过的 errors.json。
+    let reload_watch = match &cli.db {
+        Some(db_path) => watch_data_dirs(&[db_path.clone()]),
+        None => watch_data_dirs(&[data_path.clone(), notes_path]),
+    }
+    .ok();
 
     // TUI 初始化
     enable_raw_mode()?;
@@ -625,6 +914,7 @@ fn main() -> Result<()> {
 
     let mut app = App::new(
         data,
+        store,
         sources,
         cli.show_comments,
         cli.exam_date,
@@ -634,11 +924,25 @@ fn main() -> Result<()> {
         } else {
             None
         },
-        theme_of(cli.theme),
+        initial_theme,
+        cli.theme,
+        theme_registry,
+        theme_index,
         keymap,
         notes,
+        cli.semantic,
+        data_path
+            .parent()
+            .map(|p| p.join("embeddings.json"))
+            .unwrap_or_else(|| PathBuf::from("embeddings.json")),
+        cli.embeddings_endpoint
+            .clone()
+            .map(|endpoint| EmbeddingClient::new(endpoint, cli.embeddings_key.clone(), cli.embeddings_model.clone())),
+        data_path,
+        cli.db.clone(),
+        reload_watch,
     );
-    let res = run_app(&mut terminal, &mut app, &data_path);
+    let res = run_app(&mut terminal, &mut app);
 
     // 退出还原
     disable_raw_mode()?;
@@ -654,31 +958,36 @@ fn main() -> Result<()> {
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    data_path: &PathBuf,
 ) -> Result<()> {
     loop {
+        poll_file_reload(app)?;
+        poll_semantic_job(app);
         terminal.draw(|f| ui(f, app))?;
         if event::poll(Duration::from_millis(200))? {
             match event::read()? {
                 Event::Key(k) => {
+                    app.reload_notice = None;
                     // 编辑器模式下，直接交给编辑器处理
-                    if let Some(ed) = app.editor.as_mut() {
-                        if handle_editor_key(ed, &k) {
+                    if app.editor.is_some() {
+                        let completion_ctx = build_completion_ctx(app);
+                        let ed = app.editor.as_mut().unwrap();
+                        if handle_editor_key(ed, &k, &completion_ctx) {
                             // true 表示已保存/退出
                             let saved = ed.saved;
-                            let content = ed.buffer.clone();
+                            let content = ed.text();
                             if saved {
                                 if let Some(idx) = ed.target_note_index {
                                     if let Some(n) = app.notes.data.notes.get_mut(idx) {
                                         n.content = content;
+                                        n.tags = extract_tags(&n.content);
                                         n.updated_at = Utc::now().to_rfc3339();
                                     }
-                                    app.notes.save()?;
+                                    app.notes.save(app.store.as_mut())?;
                                     rebuild_note_view(app);
                                 } else if let (Some(qid), Some(excerpt)) =
                                     (ed.new_note_qid, ed.new_note_excerpt.clone())
                                 {
-                                    app.notes.add_note(qid, excerpt, content)?;
+                                    app.notes.add_note(app.store.as_mut(), qid, excerpt, content)?;
                                     rebuild_note_view(app);
                                 } // 否则忽略
                             }
@@ -686,10 +995,13 @@ fn run_app<B: ratatui::backend::Backend>(
                         }
                         continue;
                     }
-                    if handle_key(app, k, data_path)? {
+                    if handle_key(app, k)? {
                         break;
                     }
                 }
+                Event::Mouse(m) => {
+                    handle_mouse(app, m)?;
+                }
                 _ => {}
             }
         }
@@ -697,7 +1009,84 @@ fn run_app<B: ratatui::backend::Backend>(
     Ok(())
 }
 
-fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool> {
+fn rect_contains(r: Rect, x: u16, y: u16) -> bool {
+    x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+}
+
+/// 鼠标事件入口：闪卡模式下点击翻面/评分按钮；否则左键点击列表行选中对应条目，
+/// 滚轮在详情区滚动。拖动、右键等事件暂不处理。
+fn handle_mouse(app: &mut App, m: MouseEvent) -> Result<()> {
+    match m.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if app.flash_mode {
+                handle_flash_click(app, m.column, m.row)?;
+            } else if rect_contains(app.list_area, m.column, m.row)
+                && matches!(app.left_panel, LeftPanel::Questions)
+            {
+                select_list_row_at(app, app.list_area, m.row);
+            } else if rect_contains(app.notes_list_area, m.column, m.row)
+                && matches!(app.left_panel, LeftPanel::Notes)
+            {
+                select_notes_row_at(app, app.notes_list_area, m.row);
+            }
+        }
+        MouseEventKind::ScrollDown => {
+            if rect_contains(app.detail_area, m.column, m.row) {
+                scroll_right(app, 1);
+            }
+        }
+        MouseEventKind::ScrollUp => {
+            if rect_contains(app.detail_area, m.column, m.row) {
+                scroll_right(app, -1);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn select_list_row_at(app: &mut App, area: Rect, row: u16) {
+    if row <= area.y || row >= area.y + area.height.saturating_sub(1) {
+        return;
+    }
+    let n = question_visible_count(app);
+    if n == 0 {
+        return;
+    }
+    let idx = app.list_state.offset() + (row - area.y - 1) as usize;
+    app.list_state.select(Some(idx.min(n - 1)));
+}
+
+fn select_notes_row_at(app: &mut App, area: Rect, row: u16) {
+    if row <= area.y || row >= area.y + area.height.saturating_sub(1) {
+        return;
+    }
+    let n = app.filtered_note_indices.len();
+    if n == 0 {
+        return;
+    }
+    let idx = app.list_state_notes.offset() + (row - area.y - 1) as usize;
+    app.list_state_notes.select(Some(idx.min(n - 1)));
+}
+
+fn handle_flash_click(app: &mut App, x: u16, y: u16) -> Result<()> {
+    if rect_contains(app.flash_reveal_area, x, y) {
+        flash_reveal(app);
+        return Ok(());
+    }
+    for (grade, rect) in app.flash_grade_areas.clone() {
+        if rect_contains(rect, x, y) {
+            flash_grade(app, grade)?;
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
+    if app.keymap_pending_expired() {
+        app.clear_keymap_pending();
+    }
     let KeyEvent { code, .. } = key;
     match code {
         KeyCode::Char('q') => {
@@ -740,36 +1129,56 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
                 rebuild_note_view(app);
             } else if app.question_search_active && matches!(app.left_panel, LeftPanel::Questions) {
                 app.question_search_active = false;
-                app.question_search_query = None;
-                refresh_question_filter(app);
-            } else {
+                if app.semantic_mode {
+                    // 语义检索放到 Enter 才触发，而不是每敲一个字符就发一次请求；
+                    // 请求本身放到后台线程跑（见 `spawn_semantic_question_search`），
+                    // 这里先保留查询文本供后台线程使用，不像普通模式那样立即清空。
+                    let query = app.question_search_query.clone().unwrap_or_default();
+                    app.question_search_query = None;
+                    if query.trim().is_empty() {
+                        refresh_question_filter(app);
+                    } else {
+                        spawn_semantic_question_search(app, query);
+                    }
+                } else {
+                    app.question_search_query = None;
+                    refresh_question_filter(app);
+                }
+            } else if !dispatch_keymap_key(app, &key)? {
                 match app.left_panel {
-                    LeftPanel::Questions => apply_action(app, data_path, KeyAction::EnterText)?,
-                    LeftPanel::Notes => apply_action(app, data_path, KeyAction::NoteOpen)?,
+                    LeftPanel::Questions => apply_action(app, KeyAction::EnterText)?,
+                    LeftPanel::Notes => apply_action(app, KeyAction::NoteOpen)?,
                 }
             }
         }
         KeyCode::Esc => {
-            if app.note_search_active && matches!(app.left_panel, LeftPanel::Notes) {
+            if !app.keymap_pending.is_empty() {
+                // 多键组合还没等到后续按键，Esc 先把它取消掉，不触发别的 Esc 行为
+                app.clear_keymap_pending();
+            } else if app.note_search_active && matches!(app.left_panel, LeftPanel::Notes) {
                 app.note_search_active = false;
                 app.note_search_query = None;
                 rebuild_note_view(app);
             } else if app.question_search_active && matches!(app.left_panel, LeftPanel::Questions) {
                 app.question_search_active = false;
                 app.question_search_query = None;
+                app.question_search_generation += 1;
+                app.semantic_job = None;
                 refresh_question_filter(app);
-            } else {
-                apply_action(app, data_path, KeyAction::ExitText)?;
+            } else if !dispatch_keymap_key(app, &key)? {
+                apply_action(app, KeyAction::ExitText)?;
             }
         }
         KeyCode::Tab => {
-            apply_action(app, data_path, KeyAction::SwitchLeftPanel)?;
+            if !dispatch_keymap_key(app, &key)? {
+                apply_action(app, KeyAction::SwitchLeftPanel)?;
+            }
         }
         KeyCode::Char('<') => {
-            apply_action(app, data_path, KeyAction::ResizeLeftShrink)?;
+            apply_action(app, KeyAction::ResizeLeftShrink)?;
         }
         KeyCode::Char('>') => {
-            apply_action(app, data_path, KeyAction::ResizeLeftExpand)?;
+            apply_action(app, KeyAction::ResizeLeftExpand)?;
         }
         KeyCode::Char('/') => {
             if matches!(app.left_panel, LeftPanel::Notes) {
@@ -779,6 +1188,8 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
             } else if matches!(app.left_panel, LeftPanel::Questions) {
                 app.question_search_active = true;
                 app.question_search_query = Some(String::new());
+                app.question_search_generation += 1;
+                app.semantic_job = None;
                 refresh_question_filter(app);
             }
         }
@@ -860,7 +1271,7 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
         }
         // handled above in unconditional 'j'/'k'
         KeyCode::Char('v') if app.flash_mode => {
-            flash_grade(app, data_path, "easy")?;
+            flash_grade(app, "easy")?;
         }
         KeyCode::Char('V') => {
             if app.focus == Focus::Text {
@@ -899,16 +1310,16 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
             flash_prev(app);
         }
         KeyCode::Char('z') if app.flash_mode => {
-            flash_grade(app, data_path, "again")?;
+            flash_grade(app, "again")?;
         }
         KeyCode::Char('x') if app.flash_mode => {
-            flash_grade(app, data_path, "hard")?;
+            flash_grade(app, "hard")?;
         }
         KeyCode::Char('g') if app.flash_mode => {
-            flash_grade(app, data_path, "good")?;
+            flash_grade(app, "good")?;
         }
         KeyCode::Char('v') if app.flash_mode => {
-            flash_grade(app, data_path, "easy")?;
+            flash_grade(app, "easy")?;
         }
         KeyCode::Char('v') => {
             if app.focus == Focus::Text {
@@ -930,9 +1341,7 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
                 refresh_question_filter(app);
                 return Ok(false);
             }
-            if let Some(action) = app.keymap.get(&ch).cloned() {
-                apply_action(app, data_path, action)?;
-            }
+            dispatch_keymap_key(app, &key)?;
         }
         KeyCode::Backspace => {
             if app.note_search_active && matches!(app.left_panel, LeftPanel::Notes) {
@@ -945,6 +1354,8 @@ fn handle_key(app: &mut App, key: KeyEvent, data_path: &PathBuf) -> Result<bool>
                     s.pop();
                 }
                 refresh_question_filter(app);
+            } else {
+                dispatch_keymap_key(app, &key)?;
             }
         }
         // Flashcards 快捷键
@@ -999,9 +1410,13 @@ enum KeyAction {
     FlashReveal,
     FlashNext,
     FlashPrev,
+    // 语义检索
+    ToggleSemanticSearch,
+    // 主题
+    CycleTheme,
 }
 
-fn apply_action(app: &mut App, data_path: &PathBuf, action: KeyAction) -> Result<()> {
+fn apply_action(app: &mut App, action: KeyAction) -> Result<()> {
     match action {
         KeyAction::ToggleAnswerCurrent => {
             if let Some(rr) = app.selected_ref() {
@@ -1028,35 +1443,35 @@ fn apply_action(app: &mut App, data_path: &PathBuf, action: KeyAction) -> Result
         KeyAction::ToggleSourceSim => toggle_source(app, SourceKind::Simulation),
         KeyAction::ToggleSourceReal => toggle_source(app, SourceKind::Real),
         KeyAction::ToggleSourceFamous => toggle_source(app, SourceKind::Famous),
-        KeyAction::MarkNew => set_status_and_save(app, data_path, "new")?,
-        KeyAction::MarkReviewing => set_status_and_save(app, data_path, "reviewing")?,
-        KeyAction::MarkMastered => set_status_and_save(app, data_path, "mastered")?,
+        KeyAction::MarkNew => set_status_and_save(app, "new")?,
+        KeyAction::MarkReviewing => set_status_and_save(app, "reviewing")?,
+        KeyAction::MarkMastered => set_status_and_save(app, "mastered")?,
         KeyAction::GradeAgain => {
             if matches!(app.left_panel, LeftPanel::Notes) {
                 grade_note(app, "again")?;
             } else {
-                grade_and_schedule(app, data_path, "again")?;
+                grade_and_schedule(app, "again")?;
             }
         }
         KeyAction::GradeHard => {
             if matches!(app.left_panel, LeftPanel::Notes) {
                 grade_note(app, "hard")?;
             } else {
-                grade_and_schedule(app, data_path, "hard")?;
+                grade_and_schedule(app, "hard")?;
             }
         }
         KeyAction::GradeGood => {
             if matches!(app.left_panel, LeftPanel::Notes) {
                 grade_note(app, "good")?;
             } else {
-                grade_and_schedule(app, data_path, "good")?;
+                grade_and_schedule(app, "good")?;
             }
         }
         KeyAction::GradeEasy => {
             if matches!(app.left_panel, LeftPanel::Notes) {
                 grade_note(app, "easy")?;
             } else {
-                grade_and_schedule(app, data_path, "easy")?;
+                grade_and_schedule(app, "easy")?;
             }
         }
         KeyAction::ToggleDueOnly => {
@@ -1064,7 +1479,7 @@ fn apply_action(app: &mut App, data_path: &PathBuf, action: KeyAction) -> Result
             app.rebuild_rows();
         }
         KeyAction::Reload => {
-            let d = load_data(data_path)?;
+            let d = app.store.load()?;
             app.data = d;
             app.rebuild_rows();
         }
@@ -1081,7 +1496,7 @@ fn apply_action(app: &mut App, data_path: &PathBuf, action: KeyAction) -> Result
         KeyAction::ResizeLeftShrink => resize_left(app, -5),
         KeyAction::ResizeLeftExpand => resize_left(app, 5),
         KeyAction::ToggleNotesFold => toggle_notes_fold(app),
-        KeyAction::RunScraper => run_scraper(app, data_path)?,
+        KeyAction::RunScraper => run_scraper(app)?,
         KeyAction::NoteOpen => note_open_right(app),
         KeyAction::NoteEdit => note_edit(app),
         KeyAction::NoteDelete => note_delete(app)?,
@@ -1093,14 +1508,38 @@ fn apply_action(app: &mut App, data_path: &PathBuf, action: KeyAction) -> Result
         }
         KeyAction::ScrollLineDown => scroll_right(app, 1),
         KeyAction::ScrollLineUp => scroll_right(app, -1),
-        KeyAction::FlashStart => flash_start(app),
+        KeyAction::FlashStart => {
+            if !flash_start(app) {
+                app.reload_notice = Some("没有到期的复习卡片".to_string());
+            }
+        }
         KeyAction::FlashReveal => flash_reveal(app),
         KeyAction::FlashNext => flash_next(app),
         KeyAction::FlashPrev => flash_prev(app),
+        KeyAction::ToggleSemanticSearch => {
+            app.semantic_mode = !app.semantic_mode;
+            refresh_question_filter(app);
+            rebuild_note_view(app);
+        }
+        KeyAction::CycleTheme => cycle_theme(app),
     }
     Ok(())
 }
 
+/// 在已加载的主题列表中循环切换，并重新套用 `NO_COLOR`（若设置了，
+/// 切到哪个主题都还是终端默认色）。
+fn cycle_theme(app: &mut App) {
+    if app.theme_registry.is_empty() {
+        return;
+    }
+    let mono = app.theme.mono;
+    app.theme_index = (app.theme_index + 1) % app.theme_registry.len();
+    let (name, theme) = &app.theme_registry[app.theme_index];
+    app.theme = apply_no_color(*theme, mono);
+    app.theme_kind = theme_kind_from_name(name);
+    app.reload_notice = Some(format!("主题: {}", name));
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mode {
     Normal,
@@ -1196,6 +1635,7 @@ fn rebuild_flat_lines(app: &mut App) {
     app.flat_lines = lines;
     app.cursor_line = 0;
     app.cursor_col = 0;
+    app.flat_lines_generation += 1;
 }
 
 fn enter_text_focus(app: &mut App) {
@@ -1333,181 +1773,780 @@ fn yank_to_note(app: &mut App) -> Result<()> {
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditorMode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+// 多键命令（目前只有 dd/visual-r）的等待超时：超过这个时长还没等到第二个键，
+// 就视为用户放弃了这个组合，清空 pending_op 而不是一直悬挂着。
+const PENDING_OP_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// 补全弹窗的触发来源：决定候选词从哪里来、确认时替换哪一段文字。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompletionKind {
+    /// `{{` 之后，补全下一个未用过的挖空编号。
+    Cloze,
+    /// `#` 之后，补全已存在的题目 qid。
+    Qid,
+    /// 普通单词前缀，补全其他笔记用过的标签/标题。
+    Word,
+}
+
+/// 编辑器内联补全弹窗的状态：候选词 + 当前高亮项 + 要替换的文字起点。
+#[derive(Debug, Clone)]
+struct Completion {
+    kind: CompletionKind,
+    /// 要被替换掉的那段文字（触发符之后、光标之前）在当前行的起始列。
+    anchor_col: usize,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
 #[derive(Debug, Clone)]
 struct Editor {
-    buffer: String,
-    // initial: String, // 不再使用
+    lines: Vec<String>,
+    mode: EditorMode,
+    row: usize,
+    col: usize,
+    // 待补全的多键命令的首键（如 `dd` 的第一个 `d`、visual 下 `r` 等待替换字符）
+    pending_op: Option<char>,
+    pending_since: Option<Instant>,
+    visual_start: Option<(usize, usize)>,
     saved: bool,
-    cursor: usize,
     // 目标：新建或编辑
     target_note_index: Option<usize>,
     new_note_qid: Option<i64>,
     new_note_excerpt: Option<String>,
+    // 自动补全括号/引号：每项记录一次自动插入后光标所在的 (row, col)，
+    // 即紧贴在这对括号之间的位置，供 backspace 判断是否要整对删除。
+    autoclose_stack: Vec<(usize, usize)>,
+    // 当前悬浮的补全弹窗（挖空编号 / qid 引用 / 标签&标题），每次按键后重算。
+    completion: Option<Completion>,
 }
 impl Editor {
     fn new_new(qid: i64, excerpt: String) -> Self {
-        let cur = excerpt.chars().count();
-        Self {
-            buffer: excerpt.clone(),
-            saved: false,
-            cursor: cur,
-            target_note_index: None,
-            new_note_qid: Some(qid),
-            new_note_excerpt: Some(excerpt),
-        }
+        Self::from_text(excerpt.clone(), None, Some(qid), Some(excerpt))
     }
     fn new_edit(content: String, idx: usize) -> Self {
-        let cur = content.chars().count();
+        Self::from_text(content, Some(idx), None, None)
+    }
+    fn from_text(
+        text: String,
+        target_note_index: Option<usize>,
+        new_note_qid: Option<i64>,
+        new_note_excerpt: Option<String>,
+    ) -> Self {
+        let lines: Vec<String> = if text.is_empty() {
+            vec![String::new()]
+        } else {
+            text.split('\n').map(|s| s.to_string()).collect()
+        };
+        let row = lines.len() - 1;
+        let col = lines[row].chars().count();
         Self {
-            buffer: content.clone(),
+            lines,
+            mode: EditorMode::Normal,
+            row,
+            col,
+            pending_op: None,
+            pending_since: None,
+            visual_start: None,
             saved: false,
-            cursor: cur,
-            target_note_index: Some(idx),
-            new_note_qid: None,
-            new_note_excerpt: None,
+            target_note_index,
+            new_note_qid,
+            new_note_excerpt,
+            autoclose_stack: Vec::new(),
+            completion: None,
         }
     }
+    fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+    fn line_len(&self, row: usize) -> usize {
+        self.lines.get(row).map(|l| l.chars().count()).unwrap_or(0)
+    }
+    /// Normal/Visual 模式下光标不能停在换行符之后（非空行时最多到最后一个字符）。
+    fn clamp_col_normal(&mut self) {
+        let len = self.line_len(self.row);
+        self.col = if len == 0 { 0 } else { self.col.min(len - 1) };
+    }
+    fn clear_pending(&mut self) {
+        self.pending_op = None;
+        self.pending_since = None;
+    }
+    fn pending_expired(&self) -> bool {
+        self.pending_since
+            .map(|t| t.elapsed() > PENDING_OP_TIMEOUT)
+            .unwrap_or(false)
+    }
 }
 
-fn handle_editor_key(ed: &mut Editor, k: &KeyEvent) -> bool {
-    match (k.code, k.modifiers) {
-        (KeyCode::Esc, _) => {
-            ed.saved = false;
-            return true;
-        }
-        (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
-            ed.saved = true;
-            return true;
-        }
-        (KeyCode::Enter, _) => {
-            insert_char(ed, '\n');
-        }
-        (KeyCode::Backspace, _) => {
-            backspace(ed);
-        }
-        (KeyCode::Left, _) => {
-            if ed.cursor > 0 {
-                ed.cursor -= 1;
+fn handle_editor_key(ed: &mut Editor, k: &KeyEvent, ctx: &EditorCompletionCtx) -> bool {
+    if ed.pending_expired() {
+        ed.clear_pending();
+    }
+    // Ctrl-S 在任何子模式下都直接保存退出
+    if k.code == KeyCode::Char('s') && k.modifiers.contains(KeyModifiers::CONTROL) {
+        ed.saved = true;
+        return true;
+    }
+    if matches!(ed.mode, EditorMode::Insert) && ed.completion.is_some() && handle_completion_key(ed, k) {
+        return false;
+    }
+    if k.code == KeyCode::Esc {
+        match ed.mode {
+            EditorMode::Insert => {
+                ed.mode = EditorMode::Normal;
+                ed.clamp_col_normal();
+                ed.completion = None;
             }
-        }
-        (KeyCode::Right, _) => {
-            if ed.cursor < ed.buffer.chars().count() {
-                ed.cursor += 1;
+            EditorMode::Visual => {
+                ed.mode = EditorMode::Normal;
+                ed.visual_start = None;
+            }
+            EditorMode::Normal => {
+                ed.saved = false;
+                return true;
             }
         }
-        (KeyCode::Char(ch), _) => {
-            insert_char(ed, ch);
+        ed.clear_pending();
+        return false;
+    }
+    match ed.mode {
+        EditorMode::Insert => {
+            handle_insert_key(ed, k);
+            update_completion(ed, ctx);
         }
-        _ => {}
+        EditorMode::Normal => handle_normal_key(ed, k),
+        EditorMode::Visual => handle_visual_key(ed, k),
     }
     false
 }
 
-fn insert_char(ed: &mut Editor, ch: char) {
-    let mut v: Vec<char> = ed.buffer.chars().collect();
-    let pos = ed.cursor.min(v.len());
-    v.insert(pos, ch);
-    ed.cursor += 1;
-    ed.buffer = v.into_iter().collect();
+/// 补全弹窗打开时优先处理的按键：Tab/方向键切换候选，Enter 确认，Esc 关闭弹窗
+/// （不退出 Insert 模式）。返回 `true` 表示按键已被弹窗消费。
+fn handle_completion_key(ed: &mut Editor, k: &KeyEvent) -> bool {
+    let Some(comp) = ed.completion.as_mut() else {
+        return false;
+    };
+    match k.code {
+        KeyCode::Tab | KeyCode::Down => {
+            comp.selected = (comp.selected + 1) % comp.candidates.len();
+            true
+        }
+        KeyCode::BackTab | KeyCode::Up => {
+            comp.selected = if comp.selected == 0 {
+                comp.candidates.len() - 1
+            } else {
+                comp.selected - 1
+            };
+            true
+        }
+        KeyCode::Enter => {
+            confirm_completion(ed);
+            true
+        }
+        KeyCode::Esc => {
+            ed.completion = None;
+            true
+        }
+        _ => false,
+    }
 }
 
-fn backspace(ed: &mut Editor) {
-    if ed.cursor == 0 {
+/// 把选中的候选词写入光标前的触发文字区间（`anchor_col..ed.col`），
+/// 并把光标移到插入文字之后。
+fn confirm_completion(ed: &mut Editor) {
+    let Some(comp) = ed.completion.take() else {
         return;
-    }
-    let mut v: Vec<char> = ed.buffer.chars().collect();
-    let pos = ed.cursor - 1;
-    v.remove(pos);
-    ed.cursor -= 1;
-    ed.buffer = v.into_iter().collect();
+    };
+    let Some(text) = comp.candidates.get(comp.selected).cloned() else {
+        return;
+    };
+    let row = ed.row;
+    let mut chars: Vec<char> = ed.lines[row].chars().collect();
+    let start = comp.anchor_col.min(chars.len());
+    let end = ed.col.min(chars.len()).max(start);
+    let insert: Vec<char> = text.chars().collect();
+    let inserted_len = insert.len();
+    chars.splice(start..end, insert);
+    ed.lines[row] = chars.into_iter().collect();
+    ed.col = start + inserted_len;
 }
 
-fn toggle_source(app: &mut App, k: SourceKind) {
-    if let Some(pos) = app.filter_sources.iter().position(|x| *x == k) {
-        app.filter_sources.remove(pos);
-    } else {
-        app.filter_sources.push(k);
-    }
-    if app.filter_sources.is_empty() {
-        app.filter_sources = vec![SourceKind::Simulation, SourceKind::Real];
-    }
-    app.rebuild_rows();
+/// 光标所在上下文里可用的补全素材：已有题目 qid、笔记标签、笔记标题。
+/// 在主循环里每次按键前从 `App` 快照出来，避免补全逻辑直接依赖 `App`。
+struct EditorCompletionCtx {
+    existing_qids: Vec<i64>,
+    tags: Vec<String>,
+    titles: Vec<String>,
 }
 
-fn switch_left_panel(app: &mut App) {
-    app.left_panel = match app.left_panel {
-        LeftPanel::Questions => LeftPanel::Notes,
-        LeftPanel::Notes => LeftPanel::Questions,
-    };
-    match app.left_panel {
-        LeftPanel::Notes => {
-            if app.list_state_notes.selected().is_none() && note_visible_count(app) > 0 {
-                app.list_state_notes.select(Some(0));
-            }
-            rebuild_note_view(app);
-        }
-        LeftPanel::Questions => {
-            if app.list_state.selected().is_none() && !app.rows.is_empty() {
-                app.list_state.select(Some(0));
-            }
-            refresh_question_filter(app);
-        }
+fn build_completion_ctx(app: &App) -> EditorCompletionCtx {
+    let mut existing_qids: Vec<i64> = app.notes.data.notes.iter().map(|n| n.qid).collect();
+    existing_qids.sort_unstable();
+    existing_qids.dedup();
+    let mut tags: Vec<String> = app
+        .notes
+        .data
+        .notes
+        .iter()
+        .flat_map(|n| n.tags.iter().cloned())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    let mut titles: Vec<String> = app
+        .notes
+        .data
+        .notes
+        .iter()
+        .map(note_display_title)
+        .filter(|t| !t.is_empty())
+        .collect();
+    titles.sort();
+    titles.dedup();
+    EditorCompletionCtx {
+        existing_qids,
+        tags,
+        titles,
     }
 }
 
-fn resize_left(app: &mut App, delta: i16) {
-    let w = app.left_width as i16 + delta;
-    app.left_width = w.clamp(20, 80) as u16;
+/// 按光标左侧的文字重新判断该不该弹补全、弹哪种；判断不出触发条件就收起弹窗。
+fn update_completion(ed: &mut Editor, ctx: &EditorCompletionCtx) {
+    ed.completion = compute_completion(ed, ctx);
 }
 
-fn toggle_notes_fold(app: &mut App) {
-    app.note_fold_mode = match app.note_fold_mode {
-        NotesFoldMode::Full => NotesFoldMode::CurrentParent,
-        NotesFoldMode::CurrentParent => NotesFoldMode::Full,
-    };
-    rebuild_note_view(app);
-}
+fn compute_completion(ed: &Editor, ctx: &EditorCompletionCtx) -> Option<Completion> {
+    if !matches!(ed.mode, EditorMode::Insert) {
+        return None;
+    }
+    let line: Vec<char> = ed.lines.get(ed.row)?.chars().collect();
+    let col = ed.col.min(line.len());
+    let prefix: String = line[..col].iter().collect();
 
-fn note_open_right(app: &mut App) {
-    if let Some(note) = current_note(app) {
-        let mut target_index: Option<usize> = None;
-        for (i, rr) in app.rows.iter().enumerate() {
-            let q = app.get_question(rr);
-            if q.id == note.qid {
-                target_index = Some(i);
-                break;
-            }
-        }
-        if let Some(i) = target_index {
-            app.list_state.select(Some(i));
-            app.left_panel = LeftPanel::Questions;
-            enter_text_focus(app);
+    // `{{` 之后：提示下一个尚未使用的挖空编号。
+    if prefix.ends_with("{{") {
+        let used: HashSet<i64> = parse_clozes(&ed.text())
+            .iter()
+            .filter_map(|c| c.idx.trim_start_matches('c').parse::<i64>().ok())
+            .collect();
+        let mut next = 1i64;
+        while used.contains(&next) {
+            next += 1;
         }
+        return Some(Completion {
+            kind: CompletionKind::Cloze,
+            anchor_col: col,
+            candidates: vec![format!("c{}::", next)],
+            selected: 0,
+        });
     }
-}
 
-fn note_edit(app: &mut App) {
-    if let Some(idx) = current_note_index(app) {
-        if let Some(n) = app.notes.data.notes.get(idx) {
-            app.editor = Some(Editor::new_edit(n.content.clone(), idx));
+    // `#` 紧跟着数字（且数字一直连到光标处）：提示已存在的题目 qid。
+    // 只看光标正前方这一段连续数字，避免行里更早出现的 `#` 误触发。
+    let digit_run_start = prefix
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_digit())
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(prefix.len());
+    if digit_run_start > 0 && prefix[..digit_run_start].ends_with('#') {
+        let after = &prefix[digit_run_start..];
+        let anchor_col = prefix[..digit_run_start].chars().count();
+        let candidates: Vec<String> = ctx
+            .existing_qids
+            .iter()
+            .map(i64::to_string)
+            .filter(|s| s.starts_with(after))
+            .take(8)
+            .collect();
+        if !candidates.is_empty() {
+            return Some(Completion {
+                kind: CompletionKind::Qid,
+                anchor_col,
+                candidates,
+                selected: 0,
+            });
         }
+        return None;
     }
-}
 
-fn note_delete(app: &mut App) -> Result<()> {
-    if let Some(idx) = current_note_index(app) {
-        if idx < app.notes.data.notes.len() {
-            app.notes.data.notes.remove(idx);
-            app.notes.save()?;
-            rebuild_note_view(app);
+    // 普通单词前缀：从其他笔记的标签/标题里找匹配项。
+    let word_start = prefix
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map(|(i, _)| i);
+    if let Some(byte_idx) = word_start {
+        let word = &prefix[byte_idx..];
+        if word.chars().count() >= 2 {
+            let lower = word.to_lowercase();
+            let mut candidates: Vec<String> = ctx
+                .tags
+                .iter()
+                .chain(ctx.titles.iter())
+                .filter(|s| s.as_str() != word && s.to_lowercase().starts_with(&lower))
+                .cloned()
+                .collect();
+            candidates.sort();
+            candidates.dedup();
+            candidates.truncate(8);
+            if !candidates.is_empty() {
+                let anchor_col = prefix[..byte_idx].chars().count();
+                return Some(Completion {
+                    kind: CompletionKind::Word,
+                    anchor_col,
+                    candidates,
+                    selected: 0,
+                });
+            }
         }
     }
-    Ok(())
+    None
 }
 
-fn scroll_right(app: &mut App, delta: isize) {
-    let max_lines: isize = if matches!(app.left_panel, LeftPanel::Notes) {
-        current_note(app)
+fn handle_insert_key(ed: &mut Editor, k: &KeyEvent) {
+    match k.code {
+        KeyCode::Enter => split_line(ed),
+        KeyCode::Backspace => backspace(ed),
+        KeyCode::Left => {
+            if ed.col > 0 {
+                ed.col -= 1;
+            }
+        }
+        KeyCode::Right => {
+            if ed.col < ed.line_len(ed.row) {
+                ed.col += 1;
+            }
+        }
+        KeyCode::Char(ch) => insert_char(ed, ch),
+        _ => {}
+    }
+}
+
+fn handle_normal_key(ed: &mut Editor, k: &KeyEvent) {
+    // 第二个键补全 `dd`（删除整行）
+    if ed.pending_op == Some('d') {
+        ed.clear_pending();
+        if k.code == KeyCode::Char('d') {
+            delete_line(ed);
+        }
+        return;
+    }
+    let KeyCode::Char(ch) = k.code else {
+        return;
+    };
+    match ch {
+        'h' => {
+            if ed.col > 0 {
+                ed.col -= 1;
+            }
+        }
+        'l' => {
+            let len = ed.line_len(ed.row);
+            if len > 0 {
+                ed.col = (ed.col + 1).min(len - 1);
+            }
+        }
+        'j' => {
+            if ed.row + 1 < ed.lines.len() {
+                ed.row += 1;
+                ed.clamp_col_normal();
+            }
+        }
+        'k' => {
+            if ed.row > 0 {
+                ed.row -= 1;
+                ed.clamp_col_normal();
+            }
+        }
+        'w' => move_word_forward(ed),
+        'b' => move_word_backward(ed),
+        'x' => delete_char(ed),
+        'D' => delete_to_eol(ed),
+        'd' => {
+            ed.pending_op = Some('d');
+            ed.pending_since = Some(Instant::now());
+        }
+        'A' => {
+            ed.col = ed.line_len(ed.row);
+            ed.mode = EditorMode::Insert;
+        }
+        'I' => {
+            ed.col = 0;
+            ed.mode = EditorMode::Insert;
+        }
+        'i' => {
+            ed.mode = EditorMode::Insert;
+        }
+        'o' => {
+            ed.lines.insert(ed.row + 1, String::new());
+            ed.row += 1;
+            ed.col = 0;
+            ed.mode = EditorMode::Insert;
+        }
+        'O' => {
+            ed.lines.insert(ed.row, String::new());
+            ed.col = 0;
+            ed.mode = EditorMode::Insert;
+        }
+        'v' => {
+            ed.visual_start = Some((ed.row, ed.col));
+            ed.mode = EditorMode::Visual;
+        }
+        _ => {}
+    }
+}
+
+fn handle_visual_key(ed: &mut Editor, k: &KeyEvent) {
+    // visual 下的 `r` 等待下一个字符，把选区整体替换成它
+    if ed.pending_op == Some('r') {
+        ed.clear_pending();
+        if let KeyCode::Char(ch) = k.code {
+            replace_visual_selection(ed, ch);
+        }
+        return;
+    }
+    let KeyCode::Char(ch) = k.code else {
+        return;
+    };
+    match ch {
+        'h' => {
+            if ed.col > 0 {
+                ed.col -= 1;
+            }
+        }
+        'l' => {
+            let len = ed.line_len(ed.row);
+            if len > 0 {
+                ed.col = (ed.col + 1).min(len - 1);
+            }
+        }
+        'j' => {
+            if ed.row + 1 < ed.lines.len() {
+                ed.row += 1;
+                ed.clamp_col_normal();
+            }
+        }
+        'k' => {
+            if ed.row > 0 {
+                ed.row -= 1;
+                ed.clamp_col_normal();
+            }
+        }
+        'w' => move_word_forward(ed),
+        'b' => move_word_backward(ed),
+        'd' | 'x' => {
+            delete_visual_selection(ed);
+            ed.mode = EditorMode::Normal;
+            ed.visual_start = None;
+        }
+        'r' => {
+            ed.pending_op = Some('r');
+            ed.pending_since = Some(Instant::now());
+        }
+        _ => {}
+    }
+}
+
+/// 选区按 (行, 列) 排序后的 (起点, 终点)，起点 <= 终点。
+fn visual_range(ed: &Editor) -> ((usize, usize), (usize, usize)) {
+    let start = ed.visual_start.unwrap_or((ed.row, ed.col));
+    let end = (ed.row, ed.col);
+    if start <= end {
+        (start, end)
+    } else {
+        (end, start)
+    }
+}
+
+fn delete_visual_selection(ed: &mut Editor) {
+    let ((sr, sc), (er, ec)) = visual_range(ed);
+    if sr == er {
+        let mut chars: Vec<char> = ed.lines[sr].chars().collect();
+        let a = sc.min(chars.len());
+        let b = (ec + 1).min(chars.len());
+        if a < b {
+            chars.drain(a..b);
+        }
+        ed.lines[sr] = chars.into_iter().collect();
+        ed.row = sr;
+        ed.col = a.min(ed.line_len(sr).saturating_sub(1));
+    } else {
+        let mut head: Vec<char> = ed.lines[sr].chars().take(sc).collect();
+        let tail: Vec<char> = ed.lines[er].chars().skip(ec + 1).collect();
+        head.extend(tail);
+        let merged: String = head.into_iter().collect();
+        ed.lines.splice(sr..=er, std::iter::once(merged));
+        ed.row = sr;
+        ed.col = sc.min(ed.line_len(sr).saturating_sub(1));
+    }
+}
+
+fn replace_visual_selection(ed: &mut Editor, ch: char) {
+    let ((sr, sc), (er, ec)) = visual_range(ed);
+    for r in sr..=er {
+        let mut chars: Vec<char> = ed.lines[r].chars().collect();
+        let a = if r == sr { sc } else { 0 };
+        let b = if r == er { ec + 1 } else { chars.len() };
+        let a = a.min(chars.len());
+        let b = b.min(chars.len());
+        for c in chars.iter_mut().take(b).skip(a) {
+            *c = ch;
+        }
+        ed.lines[r] = chars.into_iter().collect();
+    }
+    ed.row = sr;
+    ed.col = sc;
+    ed.mode = EditorMode::Normal;
+    ed.visual_start = None;
+}
+
+fn move_word_forward(ed: &mut Editor) {
+    let line: Vec<char> = ed.lines[ed.row].chars().collect();
+    let mut i = ed.col;
+    // 跳过当前单词剩余部分，再跳过间隔空白，停在下一个单词起始处
+    while i < line.len() && !line[i].is_whitespace() {
+        i += 1;
+    }
+    while i < line.len() && line[i].is_whitespace() {
+        i += 1;
+    }
+    if i >= line.len() && ed.row + 1 < ed.lines.len() {
+        ed.row += 1;
+        ed.col = 0;
+    } else {
+        ed.col = i.min(line.len().saturating_sub(1));
+    }
+}
+
+fn move_word_backward(ed: &mut Editor) {
+    if ed.col == 0 {
+        if ed.row > 0 {
+            ed.row -= 1;
+            ed.col = ed.line_len(ed.row).saturating_sub(1);
+        }
+        return;
+    }
+    let line: Vec<char> = ed.lines[ed.row].chars().collect();
+    let mut i = ed.col.min(line.len()).saturating_sub(1);
+    while i > 0 && line[i].is_whitespace() {
+        i -= 1;
+    }
+    while i > 0 && !line[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    ed.col = i;
+}
+
+fn delete_char(ed: &mut Editor) {
+    let mut chars: Vec<char> = ed.lines[ed.row].chars().collect();
+    if ed.col < chars.len() {
+        chars.remove(ed.col);
+        ed.lines[ed.row] = chars.into_iter().collect();
+        ed.col = ed.col.min(ed.line_len(ed.row).saturating_sub(1));
+    }
+}
+
+fn delete_to_eol(ed: &mut Editor) {
+    let mut chars: Vec<char> = ed.lines[ed.row].chars().collect();
+    chars.truncate(ed.col);
+    ed.lines[ed.row] = chars.into_iter().collect();
+    ed.col = ed.col.min(ed.line_len(ed.row).saturating_sub(1));
+}
+
+fn delete_line(ed: &mut Editor) {
+    if ed.lines.len() == 1 {
+        ed.lines[0].clear();
+    } else {
+        ed.lines.remove(ed.row);
+        if ed.row >= ed.lines.len() {
+            ed.row = ed.lines.len() - 1;
+        }
+    }
+    ed.col = ed.col.min(ed.line_len(ed.row).saturating_sub(1));
+}
+
+fn split_line(ed: &mut Editor) {
+    let chars: Vec<char> = ed.lines[ed.row].chars().collect();
+    let pos = ed.col.min(chars.len());
+    let rest: String = chars[pos..].iter().collect();
+    let head: String = chars[..pos].iter().collect();
+    ed.lines[ed.row] = head;
+    ed.lines.insert(ed.row + 1, rest);
+    ed.row += 1;
+    ed.col = 0;
+}
+
+/// 自动闭合括号/引号的开符 -> 合符映射；不在表中的字符视为不参与自动闭合。
+fn autoclose_pair(ch: char) -> Option<char> {
+    match ch {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+fn insert_char(ed: &mut Editor, ch: char) {
+    let next_char = ed.lines[ed.row].chars().nth(ed.col);
+    // 光标正前方就是同一个合符/引号时直接跨过，不重复插入。
+    if matches!(ch, ')' | ']' | '}' | '"' | '\'') && next_char == Some(ch) {
+        ed.col += 1;
+        return;
+    }
+    if let Some(close) = autoclose_pair(ch) {
+        // 紧邻的下一个字符是单词字符时，说明这是在已有文本中间打字，
+        // 不自动补合符，避免把现有单词拆开。
+        let next_is_word = next_char
+            .map(|c| c.is_alphanumeric() || c == '_')
+            .unwrap_or(false);
+        if !next_is_word {
+            let mut chars: Vec<char> = ed.lines[ed.row].chars().collect();
+            let pos = ed.col.min(chars.len());
+            chars.insert(pos, ch);
+            chars.insert(pos + 1, close);
+            ed.lines[ed.row] = chars.into_iter().collect();
+            ed.col += 1;
+            ed.autoclose_stack.push((ed.row, ed.col));
+            return;
+        }
+    }
+    let mut chars: Vec<char> = ed.lines[ed.row].chars().collect();
+    let pos = ed.col.min(chars.len());
+    chars.insert(pos, ch);
+    ed.lines[ed.row] = chars.into_iter().collect();
+    ed.col += 1;
+}
+
+fn backspace(ed: &mut Editor) {
+    if ed.col > 0 {
+        // 光标正好停在一对刚刚自动插入、中间还没有内容的括号/引号之间时，
+        // 一次性把两个字符都删掉，而不是只删开符前的字符。
+        if let Some(&(row, col)) = ed.autoclose_stack.last() {
+            if row == ed.row && col == ed.col {
+                let chars: Vec<char> = ed.lines[ed.row].chars().collect();
+                let is_empty_pair = chars
+                    .get(ed.col - 1)
+                    .zip(chars.get(ed.col))
+                    .is_some_and(|(&open, &close)| autoclose_pair(open) == Some(close));
+                if is_empty_pair {
+                    let mut chars = chars;
+                    chars.remove(ed.col);
+                    chars.remove(ed.col - 1);
+                    ed.lines[ed.row] = chars.into_iter().collect();
+                    ed.col -= 1;
+                    ed.autoclose_stack.pop();
+                    return;
+                }
+            }
+        }
+        let mut chars: Vec<char> = ed.lines[ed.row].chars().collect();
+        chars.remove(ed.col - 1);
+        ed.lines[ed.row] = chars.into_iter().collect();
+        ed.col -= 1;
+    } else if ed.row > 0 {
+        let cur = ed.lines.remove(ed.row);
+        ed.row -= 1;
+        ed.col = ed.line_len(ed.row);
+        ed.lines[ed.row].push_str(&cur);
+    }
+}
+
+fn toggle_source(app: &mut App, k: SourceKind) {
+    if let Some(pos) = app.filter_sources.iter().position(|x| *x == k) {
+        app.filter_sources.remove(pos);
+    } else {
+        app.filter_sources.push(k);
+    }
+    if app.filter_sources.is_empty() {
+        app.filter_sources = vec![SourceKind::Simulation, SourceKind::Real];
+    }
+    app.rebuild_rows();
+}
+
+fn switch_left_panel(app: &mut App) {
+    app.left_panel = match app.left_panel {
+        LeftPanel::Questions => LeftPanel::Notes,
+        LeftPanel::Notes => LeftPanel::Questions,
+    };
+    match app.left_panel {
+        LeftPanel::Notes => {
+            if app.list_state_notes.selected().is_none() && note_visible_count(app) > 0 {
+                app.list_state_notes.select(Some(0));
+            }
+            rebuild_note_view(app);
+        }
+        LeftPanel::Questions => {
+            if app.list_state.selected().is_none() && !app.rows.is_empty() {
+                app.list_state.select(Some(0));
+            }
+            refresh_question_filter(app);
+        }
+    }
+}
+
+fn resize_left(app: &mut App, delta: i16) {
+    let w = app.left_width as i16 + delta;
+    app.left_width = w.clamp(20, 80) as u16;
+}
+
+fn toggle_notes_fold(app: &mut App) {
+    app.note_fold_mode = match app.note_fold_mode {
+        NotesFoldMode::Full => NotesFoldMode::CurrentParent,
+        NotesFoldMode::CurrentParent => NotesFoldMode::Full,
+    };
+    rebuild_note_view(app);
+}
+
+fn note_open_right(app: &mut App) {
+    if let Some(note) = current_note(app) {
+        let mut target_index: Option<usize> = None;
+        for (i, rr) in app.rows.iter().enumerate() {
+            let q = app.get_question(rr);
+            if q.id == note.qid {
+                target_index = Some(i);
+                break;
+            }
+        }
+        if let Some(i) = target_index {
+            app.list_state.select(Some(i));
+            app.left_panel = LeftPanel::Questions;
+            enter_text_focus(app);
+        }
+    }
+}
+
+fn note_edit(app: &mut App) {
+    if let Some(idx) = current_note_index(app) {
+        if let Some(n) = app.notes.data.notes.get(idx) {
+            app.editor = Some(Editor::new_edit(n.content.clone(), idx));
+        }
+    }
+}
+
+fn note_delete(app: &mut App) -> Result<()> {
+    if let Some(idx) = current_note_index(app) {
+        if idx < app.notes.data.notes.len() {
+            app.notes.data.notes.remove(idx);
+            app.notes.save(app.store.as_mut())?;
+            rebuild_note_view(app);
+        }
+    }
+    Ok(())
+}
+
+fn scroll_right(app: &mut App, delta: isize) {
+    let max_lines: isize = if matches!(app.left_panel, LeftPanel::Notes) {
+        current_note(app)
             .map(|n| n.content.lines().count() as isize)
             .unwrap_or(0)
     } else {
@@ -1534,96 +2573,110 @@ fn grade_note(app: &mut App, grade: &str) -> Result<()> {
         apply_exam_grade(&mut ex, grade, None);
         note.exam = Some(ex);
         note.updated_at = Utc::now().to_rfc3339();
-        app.notes.save()?;
+        app.notes.save(app.store.as_mut())?;
     }
     Ok(())
 }
 
 // ------------- Flashcards -------------
-fn flash_start(app: &mut App) {
-    match app.left_panel {
-        LeftPanel::Notes => flash_start_notes(app),
-        LeftPanel::Questions => flash_start_question(app),
-    }
-}
+/// 构建一次复习队列：遍历全部笔记和题目（不再局限于当前选中项）的每个
+/// cloze，按 `exam_by_cloze` 里的到期时间过滤出"已到期或全新"的卡片 ——
+/// 到期的按到期时间升序（最逾期的排最前）排在队首，全新卡片（从未复习过，
+/// 没有 due）在笔记和题目之间轮流交替排在队尾，避免同一来源扎堆。
+fn build_review_queue(app: &App) -> Vec<FlashCardSource> {
+    let now = Utc::now();
+    let mut due: Vec<(chrono::DateTime<Utc>, FlashCardSource)> = Vec::new();
+    let mut fresh_notes: Vec<FlashCardSource> = Vec::new();
+    let mut fresh_questions: Vec<FlashCardSource> = Vec::new();
 
-fn flash_start_notes(app: &mut App) {
-    if let Some(idx) = current_note_index(app) {
-        if let Some(n) = app.notes.data.notes.get(idx) {
-            let clozes = parse_clozes(&n.content);
-            if clozes.is_empty() {
-                return;
-            }
-            let mut cards = Vec::new();
-            let mut seen = std::collections::HashSet::new();
-            for c in clozes {
-                if seen.insert(c.idx.clone()) {
-                    cards.push(FlashCardSource::Note {
-                        note_idx: idx,
-                        cloze: c.idx,
-                    });
-                }
+    for (note_idx, note) in app.notes.data.notes.iter().enumerate() {
+        let mut seen = std::collections::HashSet::new();
+        for c in parse_clozes(&note.content) {
+            if !seen.insert(c.idx.clone()) {
+                continue;
+            }
+            let card = FlashCardSource::Note {
+                note_idx,
+                cloze: c.idx.clone(),
+            };
+            match note
+                .exam_by_cloze
+                .get(&c.idx)
+                .and_then(|ex| ex.due.as_deref())
+                .and_then(parse_rfc3339)
+            {
+                Some(d) if d <= now => due.push((d, card)),
+                Some(_) => {}
+                None => fresh_notes.push(card),
             }
-            app.flash_cards = cards;
-            app.flash_pos = 0;
-            app.flash_revealed = false;
-            app.flash_mode = true;
         }
     }
-}
 
-fn flash_start_question(app: &mut App) {
-    if let Some(rr) = app.selected_ref() {
-        let q = app.get_question(rr);
-        if q.answer.is_empty() {
-            return;
-        }
-        let mut cards = Vec::new();
-        let mut seen = std::collections::HashSet::new();
+    for row in &app.rows {
+        let q = app.get_question(row);
         let answers: Vec<String> = q
             .answer
             .iter()
-            .filter_map(|ans| {
-                let trimmed = ans.trim();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some(ans.clone())
-                }
-            })
+            .filter(|ans| !ans.trim().is_empty())
+            .cloned()
             .collect();
         if answers.is_empty() {
-            return;
+            continue;
         }
-        if answers.len() > 1 {
-            let cloze = "multi".to_string();
-            if seen.insert(cloze.clone()) {
-                cards.push(FlashCardSource::Question {
-                    row: rr.clone(),
-                    cloze,
-                    answers: answers.clone(),
-                    is_multi: true,
-                });
-            }
+        let (cloze, is_multi) = if answers.len() > 1 {
+            ("multi".to_string(), true)
         } else {
-            let cloze = "a1".to_string();
-            if seen.insert(cloze.clone()) {
-                cards.push(FlashCardSource::Question {
-                    row: rr.clone(),
-                    cloze,
-                    answers: answers.clone(),
-                    is_multi: false,
-                });
-            }
+            ("a1".to_string(), false)
+        };
+        let card = FlashCardSource::Question {
+            row: row.clone(),
+            cloze: cloze.clone(),
+            answers,
+            is_multi,
+        };
+        match q
+            .exam_by_cloze
+            .get(&cloze)
+            .and_then(|ex| ex.due.as_deref())
+            .and_then(parse_rfc3339)
+        {
+            Some(d) if d <= now => due.push((d, card)),
+            Some(_) => {}
+            None => fresh_questions.push(card),
         }
-        if cards.is_empty() {
-            return;
+    }
+
+    due.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut queue: Vec<FlashCardSource> = due.into_iter().map(|(_, c)| c).collect();
+
+    let mut notes_iter = fresh_notes.into_iter();
+    let mut questions_iter = fresh_questions.into_iter();
+    loop {
+        let n = notes_iter.next();
+        let q = questions_iter.next();
+        if n.is_none() && q.is_none() {
+            break;
         }
-        app.flash_cards = cards;
-        app.flash_pos = 0;
-        app.flash_revealed = false;
-        app.flash_mode = true;
+        queue.extend(n);
+        queue.extend(q);
+    }
+    queue
+}
+
+/// 开始一轮闪卡复习；队列为空（没有到期/新卡片）时不进入闪卡模式，
+/// 返回 `false` 让调用方决定要不要提示用户。
+/// SM-2 调度本身（到期计算、`n`/`EF`/`I` 更新）在 `build_review_queue` 和
+/// `apply_exam_grade` 里，这里只负责“队列是空的”这一种情况的提示。
+fn flash_start(app: &mut App) -> bool {
+    let queue = build_review_queue(app);
+    if queue.is_empty() {
+        return false;
     }
+    app.flash_cards = queue;
+    app.flash_pos = 0;
+    app.flash_revealed = false;
+    app.flash_mode = true;
+    true
 }
 
 fn flash_reveal(app: &mut App) {
@@ -1667,11 +2720,13 @@ fn flash_toggle(app: &mut App) {
         app.flash_mode = false;
         app.flash_revealed = false;
     } else {
-        flash_start(app);
+        if !flash_start(app) {
+            app.reload_notice = Some("没有到期的复习卡片".to_string());
+        }
     }
 }
 
-fn flash_grade(app: &mut App, data_path: &PathBuf, grade: &str) -> Result<()> {
+fn flash_grade(app: &mut App, grade: &str) -> Result<()> {
     if !app.flash_mode || app.flash_cards.is_empty() {
         return Ok(());
     }
@@ -1685,11 +2740,11 @@ fn flash_grade(app: &mut App, data_path: &PathBuf, grade: &str) -> Result<()> {
                     .or_insert_with(default_exam_state);
                 apply_exam_grade(entry, grade, None);
                 note.updated_at = Utc::now().to_rfc3339();
-                app.notes.save()?;
+                app.notes.save(app.store.as_mut())?;
             }
         }
         FlashCardSource::Question { ref row, cloze, .. } => {
-            grade_and_schedule(app, data_path, grade)?;
+            grade_and_schedule(app, grade)?;
             let exam_date = app.exam_date;
             let q = app.get_question_mut(row);
             let entry = q
@@ -1706,18 +2761,19 @@ fn flash_grade(app: &mut App, data_path: &PathBuf, grade: &str) -> Result<()> {
     Ok(())
 }
 
-fn set_status_and_save(app: &mut App, data_path: &PathBuf, status: &str) -> Result<()> {
+fn set_status_and_save(app: &mut App, status: &str) -> Result<()> {
     if let Some(idx) = app.list_state.selected() {
         let rr = app.rows[idx].clone();
         let q = app.get_question_mut(&rr);
         q.user_status = status.into();
         q.last_reviewed = Some(Utc::now().to_rfc3339());
-        save_data(data_path, &app.data)?;
+        let snapshot = q.clone();
+        app.store.save_question(&app.data, rr.src, &snapshot)?;
     }
     Ok(())
 }
 
-fn run_scraper(app: &mut App, data_path: &PathBuf) -> Result<()> {
+fn run_scraper(app: &mut App) -> Result<()> {
     let scraper = Path::new("../backend/scraper.py");
     let status = Command::new("python3")
         .arg(scraper)
@@ -1726,7 +2782,7 @@ fn run_scraper(app: &mut App, data_path: &PathBuf) -> Result<()> {
     if !status.success() {
         return Err(anyhow::anyhow!("scraper 返回非 0 退出码"));
     }
-    let d = load_data(data_path)?;
+    let d = app.store.load()?;
     app.data = d;
     app.rebuild_rows();
     Ok(())
@@ -1764,30 +2820,125 @@ fn ui(f: &mut Frame, app: &mut App) {
     if let Some(ed) = app.editor.as_ref() {
         let area = centered_rect(70, 60, f.area());
         f.render_widget(Clear, area);
+        let mode_label = match ed.mode {
+            EditorMode::Normal => "NORMAL",
+            EditorMode::Insert => "INSERT",
+            EditorMode::Visual => "VISUAL",
+        };
         let block = Block::default()
             .title(Span::styled(
-                " 新建笔记  [Ctrl+S 保存 / Esc 取消 | ←/→ 光标] ",
+                format!(" 编辑笔记 [{}]  [Ctrl+S 保存 / Esc 返回上一级] ", mode_label),
                 Style::default().fg(app.theme.accent),
             ))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(app.theme.muted));
-        // 画出编辑器光标
-        let chars: Vec<char> = ed.buffer.chars().collect();
-        let a = ed.cursor.min(chars.len());
-        let left: String = chars[0..a].iter().collect();
-        let right: String = chars[a..].iter().collect();
-        let composed = vec![Line::from(vec![
-            Span::raw(left),
-            Span::styled("▏", Style::default().fg(app.theme.accent)),
-            Span::raw(right),
-        ])];
+        let composed = render_editor_lines(ed, app.theme);
         let para = Paragraph::new(composed)
             .block(block)
             .wrap(Wrap { trim: false });
         f.render_widget(para, area);
+        if let Some(comp) = ed.completion.as_ref() {
+            draw_completion_popup(f, area, ed, comp, app.theme);
+        }
     }
 }
 
+/// 在编辑器光标正下方画一个小悬浮列表，展示补全候选词，当前高亮项用选中色。
+fn draw_completion_popup(f: &mut Frame, editor_area: Rect, ed: &Editor, comp: &Completion, th: Theme) {
+    let screen = f.area();
+    let inner_x = editor_area.x + 1;
+    let inner_y = editor_area.y + 1;
+    let cursor_x = inner_x + ed.col as u16;
+    let cursor_y = inner_y + ed.row as u16;
+    let title = match comp.kind {
+        CompletionKind::Cloze => " 挖空编号 ",
+        CompletionKind::Qid => " 题目引用 ",
+        CompletionKind::Word => " 标签/标题 ",
+    };
+    let width = comp
+        .candidates
+        .iter()
+        .map(|c| c.chars().count() as u16)
+        .max()
+        .unwrap_or(4)
+        .saturating_add(4)
+        .max(title.chars().count() as u16 + 2)
+        .min(screen.width.saturating_sub(1).max(1));
+    let height = (comp.candidates.len() as u16 + 2).min(8);
+    let popup_y = if cursor_y + 1 + height <= screen.y + screen.height {
+        cursor_y + 1
+    } else {
+        cursor_y.saturating_sub(height)
+    };
+    let popup_x = cursor_x.min(screen.x + screen.width.saturating_sub(width));
+    let popup = Rect {
+        x: popup_x,
+        y: popup_y,
+        width,
+        height,
+    };
+    f.render_widget(Clear, popup);
+    let items: Vec<ListItem> = comp
+        .candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let style = if i == comp.selected {
+                Style::default().bg(th.selection_bg).fg(th.fg)
+            } else {
+                Style::default().fg(th.fg)
+            };
+            ListItem::new(Line::from(Span::styled(c.clone(), style)))
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .title(Span::styled(title, Style::default().fg(th.accent)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(th.muted)),
+    );
+    f.render_widget(list, popup);
+}
+
+fn render_editor_lines(ed: &Editor, th: Theme) -> Vec<Line<'static>> {
+    let sel_range = matches!(ed.mode, EditorMode::Visual).then(|| visual_range(ed));
+    ed.lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let chars: Vec<char> = line.chars().collect();
+            let mut spans: Vec<Span<'static>> = Vec::new();
+            for (col, ch) in chars.iter().enumerate() {
+                let in_sel = sel_range
+                    .map(|((sr, sc), (er, ec))| in_visual_range(row, col, sr, sc, er, ec))
+                    .unwrap_or(false);
+                let is_cursor =
+                    row == ed.row && col == ed.col && !matches!(ed.mode, EditorMode::Insert);
+                let mut style = Style::default().fg(th.fg);
+                if in_sel {
+                    style = style.bg(th.selection_bg);
+                }
+                if is_cursor {
+                    style = style.bg(th.accent).fg(Color::Black);
+                }
+                spans.push(Span::styled(ch.to_string(), style));
+            }
+            if row == ed.row && ed.col >= chars.len() {
+                if matches!(ed.mode, EditorMode::Insert) {
+                    spans.push(Span::styled("▏", Style::default().fg(th.accent)));
+                } else {
+                    spans.push(Span::styled(" ", Style::default().bg(th.accent)));
+                }
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn in_visual_range(row: usize, col: usize, sr: usize, sc: usize, er: usize, ec: usize) -> bool {
+    (row > sr || (row == sr && col >= sc)) && (row < er || (row == er && col <= ec))
+}
+
 fn draw_flashcard_fullscreen(f: &mut Frame, app: &mut App) {
     let th = app.theme;
     let area = f.area();
@@ -1800,34 +2951,72 @@ fn draw_flashcard_fullscreen(f: &mut Frame, app: &mut App) {
         return;
     }
     let card = &app.flash_cards[app.flash_pos];
-    let inner = Rect {
+    let full_inner = Rect {
         x: area.x + 1,
         y: area.y + 1,
         width: area.width.saturating_sub(2),
         height: area.height.saturating_sub(2),
     };
-    let (notes, single, multi) = flashcard_counts(app);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(full_inner);
+    let inner = rows[0];
+    app.flash_reveal_area = inner;
+    let button_row = rows[1];
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+        ])
+        .split(button_row);
+    app.flash_grade_areas = vec![
+        ("again", buttons[0]),
+        ("hard", buttons[1]),
+        ("good", buttons[2]),
+        ("easy", buttons[3]),
+    ];
+    let button_line = Line::from(vec![
+        Span::styled(" Again ", Style::default().fg(th.warn)),
+        Span::styled(" Hard ", Style::default().fg(th.muted)),
+        Span::styled(" Good ", Style::default().fg(th.good)),
+        Span::styled(" Easy ", Style::default().fg(th.info)),
+    ]);
+    f.render_widget(
+        Paragraph::new(button_line).alignment(Alignment::Center),
+        button_row,
+    );
+    let (new_count, learning_count, review_count) = flashcard_counts(app);
     let stats_line = Line::from(vec![
-        Span::styled(format!("[New:{}] ", notes), Style::default().fg(th.info)),
         Span::styled(
-            format!("[Learning:{}] ", single),
+            format!("[New:{}] ", new_count),
+            Style::default().fg(th.info),
+        ),
+        Span::styled(
+            format!("[Learning:{}] ", learning_count),
             Style::default().fg(th.good),
         ),
-        Span::styled(format!("[Review:{}]", multi), Style::default().fg(th.warn)),
+        Span::styled(
+            format!("[Review:{}]", review_count),
+            Style::default().fg(th.warn),
+        ),
     ]);
     let body_lines = match card {
         FlashCardSource::Note { note_idx, cloze } => {
             if let Some(n) = app.notes.data.notes.get(*note_idx) {
                 let masked = mask_cloze(&n.content, cloze, app.flash_revealed);
-                let header = format!(
-                    "{} · {} ({}/{})",
-                    note_display_title(n),
-                    cloze,
-                    app.flash_pos + 1,
-                    app.flash_cards.len(),
-                );
+                let header_ctx = FlashNoteContext {
+                    title: note_display_title(n),
+                    cloze: cloze.clone(),
+                    pos: app.flash_pos + 1,
+                    count: app.flash_cards.len(),
+                };
+                let header = app.templates.render_flash_note_header(&header_ctx);
                 vec![
-                    Line::from(Span::styled(header, Style::default().fg(th.fg))),
+                    Line::from(spans_from_template(&header, &th, Style::default().fg(th.fg))),
                     Line::from(Span::raw(" ")),
                     Line::from(Span::raw(masked)),
                 ]
@@ -1861,17 +3050,15 @@ fn draw_flashcard_fullscreen(f: &mut Frame, app: &mut App) {
             };
             let options = format_question_options(q);
             let schedule = format_question_schedule(q);
+            let header_ctx = FlashQuestionContext {
+                id: q.id,
+                label,
+                answered: answers.len(),
+                total: answers.len().max(1),
+            };
+            let header = app.templates.render_flash_question_header(&header_ctx);
             let mut lines = vec![
-                Line::from(Span::styled(
-                    format!(
-                        "qid:{} {} · {}/{}",
-                        q.id,
-                        label,
-                        answers.len(),
-                        answers.len().max(1)
-                    ),
-                    Style::default().fg(th.fg),
-                )),
+                Line::from(spans_from_template(&header, &th, Style::default().fg(th.fg))),
                 Line::from(Span::styled(schedule, Style::default().fg(th.muted))),
             ];
             if !options.is_empty() {
@@ -1889,6 +3076,83 @@ fn draw_flashcard_fullscreen(f: &mut Frame, app: &mut App) {
     f.render_widget(para, inner);
 }
 
+/// 按 `Theme` 字段名查颜色，供模板里的 `color` 帮助函数使用；未知名字退回 `fg`。
+fn theme_color(th: &Theme, name: &str) -> Color {
+    match name {
+        "muted" => th.muted,
+        "accent" => th.accent,
+        "bar_bg" => th.bar_bg,
+        "selection_bg" => th.selection_bg,
+        "good" => th.good,
+        "warn" => th.warn,
+        "info" => th.info,
+        _ => th.fg,
+    }
+}
+
+/// 选区样式：无色模式下靠反显表达，不依赖 `th.selection_bg` 这种填充色。
+fn selection_style(th: &Theme) -> Style {
+    if th.mono {
+        Style::default().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::default().bg(th.selection_bg)
+    }
+}
+
+/// 光标整块样式（Visual 模式）：无色模式下用反显 + 加粗代替 fg/bg 同色填充。
+fn cursor_block_style(th: &Theme) -> Style {
+    if th.mono {
+        Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+    } else {
+        Style::default().fg(th.accent).bg(th.accent)
+    }
+}
+
+/// 光标细竖线样式（Normal 模式）：无色模式下改用加粗表达，而不是 `th.accent`。
+fn cursor_bar_style(th: &Theme) -> Style {
+    if th.mono {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(th.accent)
+    }
+}
+
+/// 把 `Templates` 渲染结果里 `color_helper` 留下的标记切回 `Span`，未着色的
+/// 片段使用 `default_style`。
+fn spans_from_template<'a>(rendered: &str, th: &Theme, default_style: Style) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut chars = rendered.chars();
+    while let Some(c) = chars.next() {
+        if c == COLOR_START {
+            if !plain.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut plain), default_style));
+            }
+            let mut color_name = String::new();
+            for c2 in chars.by_ref() {
+                if c2 == COLOR_SEP {
+                    break;
+                }
+                color_name.push(c2);
+            }
+            let mut text = String::new();
+            for c2 in chars.by_ref() {
+                if c2 == COLOR_END {
+                    break;
+                }
+                text.push(c2);
+            }
+            spans.push(Span::styled(text, Style::default().fg(theme_color(th, &color_name))));
+        } else {
+            plain.push(c);
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::styled(plain, default_style));
+    }
+    spans
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let vert = Layout::default()
         .direction(Direction::Vertical)
@@ -1910,56 +3174,59 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 }
 
 fn draw_list(f: &mut Frame, area: Rect, app: &mut App) {
+    app.list_area = area;
     let th = app.theme;
-    let visible_rows: Vec<&RowRef> = app
+    let visible_rows: Vec<(usize, &RowRef)> = app
         .question_filtered_indices
         .iter()
-        .filter_map(|&idx| app.rows.get(idx))
+        .filter_map(|&idx| app.rows.get(idx).map(|rr| (idx, rr)))
         .collect();
 
     let items: Vec<ListItem> = visible_rows
         .into_iter()
-        .map(|rr| {
+        .map(|(idx, rr)| {
             let q = app.get_question(rr);
             let id = q.id;
             let src = q.source.clone().unwrap_or_else(|| rr.src.as_str().into());
             let origin = q.origin_name.clone();
             let sub = q.sub_name.clone();
             let status = q.user_status.clone();
-            let mut spans = Vec::new();
             let icon = match status.as_str() {
                 "mastered" => "✅",
                 "reviewing" => "🔄",
                 _ => "🆕",
             };
-            let src_color = match src.as_str() {
-                "simulation" => Color::LightBlue,
-                "real" => Color::Magenta,
-                _ => Color::Yellow,
-            };
             let status_color = match status.as_str() {
-                "mastered" => th.good,
-                "reviewing" => th.warn,
-                _ => th.muted,
+                "mastered" => "good",
+                "reviewing" => "warn",
+                _ => "muted",
             };
-            spans.push(Span::styled("› ", Style::default().fg(th.accent)));
-            spans.push(Span::raw(icon));
-            spans.push(Span::styled(
-                format!(" {:>6}  ", id),
-                Style::default().fg(th.muted),
-            ));
-            spans.push(Span::styled(
-                format!(" {} ", src),
-                Style::default().fg(src_color),
-            ));
-            spans.push(Span::styled(" | ", Style::default().fg(th.muted)));
-            spans.push(Span::styled(origin, Style::default().fg(th.fg)));
-            spans.push(Span::raw(" - "));
-            spans.push(Span::styled(sub, Style::default().fg(th.muted)));
-            spans.push(Span::styled("  ", Style::default()));
-            spans.push(Span::styled(status, Style::default().fg(status_color)));
-            if q.answer.len() > 1 {
-                spans.push(Span::styled("  【多选题】", Style::default().fg(th.warn)));
+            let ctx = ListRowContext {
+                icon: icon.to_string(),
+                id: id.to_string(),
+                source: src,
+                origin,
+                sub,
+                status,
+                status_color: status_color.to_string(),
+                multi: q.answer.len() > 1,
+            };
+            let rendered = app.templates.render_list_row(&ctx);
+            let mut spans = spans_from_template(&rendered, &th, Style::default().fg(th.fg));
+            let preview = question_content_preview(q);
+            if !preview.is_empty() {
+                let hits = app
+                    .question_search_highlights
+                    .get(&idx)
+                    .cloned()
+                    .unwrap_or_default();
+                spans.push(Span::styled("  · ", Style::default().fg(th.muted)));
+                spans.extend(highlighted_spans(
+                    &preview,
+                    &hits,
+                    Style::default().fg(th.muted),
+                    Style::default().fg(th.accent).add_modifier(Modifier::BOLD),
+                ));
             }
             ListItem::new(Line::from(spans))
         })
@@ -1992,7 +3259,39 @@ fn draw_left_panel(f: &mut Frame, area: Rect, app: &mut App) {
     }
 }
 
+/// 把 `text` 按 `hits`（命中的 char 下标）拆成若干段，命中字符用 `hit_style`，
+/// 其余沿用 `base_style`；用于笔记列表里把模糊搜索命中的片段高亮出来。
+fn highlighted_spans<'a>(text: &'a str, hits: &[usize], base_style: Style, hit_style: Style) -> Vec<Span<'a>> {
+    if hits.is_empty() {
+        return vec![Span::styled(text, base_style)];
+    }
+    let hit_set: HashSet<usize> = hits.iter().copied().collect();
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_is_hit = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_hit = hit_set.contains(&i);
+        if !run.is_empty() && is_hit != run_is_hit {
+            spans.push(Span::styled(
+                run.clone(),
+                if run_is_hit { hit_style } else { base_style },
+            ));
+            run.clear();
+        }
+        run_is_hit = is_hit;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(
+            run,
+            if run_is_hit { hit_style } else { base_style },
+        ));
+    }
+    spans
+}
+
 fn draw_notes_list(f: &mut Frame, area: Rect, app: &mut App) {
+    app.notes_list_area = area;
     let th = app.theme;
     let mut items: Vec<ListItem> = Vec::new();
     for (pos, &idx) in app.filtered_note_indices.iter().enumerate() {
@@ -2010,14 +3309,25 @@ fn draw_notes_list(f: &mut Frame, area: Rect, app: &mut App) {
                 Style::default().fg(th.info),
             ));
             spans.push(Span::raw(indent));
-            spans.push(Span::styled(
-                note_display_title(n),
+            let highlights = app.note_search_highlights.get(&idx);
+            let title = note_display_title(n);
+            let title_hits = highlights.map(|(t, _)| t.as_slice()).unwrap_or(&[]);
+            spans.extend(highlighted_spans(
+                &title,
+                title_hits,
                 Style::default().fg(th.fg),
+                Style::default().fg(th.accent).add_modifier(Modifier::BOLD),
             ));
             let excerpt = note_excerpt_head(n);
             if !excerpt.is_empty() {
                 spans.push(Span::styled(" · ", Style::default().fg(th.muted)));
-                spans.push(Span::styled(excerpt, Style::default().fg(th.muted)));
+                let excerpt_hits = highlights.map(|(_, e)| e.as_slice()).unwrap_or(&[]);
+                spans.extend(highlighted_spans(
+                    &excerpt,
+                    excerpt_hits,
+                    Style::default().fg(th.muted),
+                    Style::default().fg(th.accent).add_modifier(Modifier::BOLD),
+                ));
             }
             items.push(ListItem::new(Line::from(spans)));
         }
@@ -2045,7 +3355,19 @@ fn draw_notes_list(f: &mut Frame, area: Rect, app: &mut App) {
     f.render_stateful_widget(list, area, &mut app.list_state_notes);
 }
 
+/// `draw_detail` 的 Text Focus 换行缓存：只要 `flat_lines` 没有换代、可视
+/// 区宽度和主题都没变，就复用上一帧算好的换行结果和精确内容长度，不用每帧
+/// 都重新分词、重建 `TextArea`。
+struct DetailCache {
+    generation: u64,
+    width: usize,
+    theme_index: usize,
+    row_counts: Vec<usize>,
+    content_len: usize,
+}
+
 fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
+    app.detail_area = area;
     let th = app.theme;
     let mut lines: Vec<Line> = vec![];
     if matches!(app.left_panel, LeftPanel::Notes) {
@@ -2077,7 +3399,7 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
                     Style::default().fg(th.warn),
                 )));
             }
-            lines.push(Line::from(Span::raw(q.content.clone())));
+            lines.extend(highlight_text(&q.content, app.theme_kind, th.fg, th.accent));
             lines.push(Line::from(" "));
             if !q.options.is_empty() {
                 lines.push(Line::from(Span::styled(
@@ -2104,7 +3426,7 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
                         "解析:",
                         Style::default().add_modifier(Modifier::BOLD).fg(th.info),
                     )));
-                    lines.push(Line::from(Span::raw(q.analysis.clone())));
+                    lines.extend(highlight_text(&q.analysis, app.theme_kind, th.fg, th.accent));
                     lines.push(Line::from(" "));
                 }
             }
@@ -2115,7 +3437,12 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
                     Style::default().add_modifier(Modifier::BOLD).fg(th.info),
                 )));
                 for c in &q.comments {
-                    lines.push(Line::from(Span::raw(format!("- {}", c))));
+                    lines.extend(highlight_text(
+                        &format!("- {}", c),
+                        app.theme_kind,
+                        th.fg,
+                        th.accent,
+                    ));
                 }
             }
         }
@@ -2133,23 +3460,40 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
     }
     if matches!(app.focus, Focus::Text) {
         let inner_width = area.width.saturating_sub(2) as usize;
-        let (wrapped_lines, row_counts) = wrap_flat_lines(&app.flat_lines, inner_width);
-        app.textarea = TextArea::from(wrapped_lines);
-        app.textarea.set_block(
-            ratatui::widgets::block::Block::default()
-                .title(Span::styled(
-                    " 详情（Text Focus）",
-                    Style::default().fg(th.accent),
-                ))
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(th.muted)),
-        );
-        app.textarea.set_cursor_line_style(Style::default());
-        app.textarea
-            .set_cursor_style(Style::default().bg(app.theme.accent).fg(Color::Black));
-        app.textarea
-            .set_selection_style(Style::default().bg(app.theme.selection_bg));
-        let content_len = apply_textarea_scroll(app, &row_counts, inner_width);
+        let stale = app.detail_cache.as_ref().map_or(true, |c| {
+            c.generation != app.flat_lines_generation
+                || c.width != inner_width
+                || c.theme_index != app.theme_index
+        });
+        if stale {
+            let (wrapped_lines, row_counts) = wrap_flat_lines(&app.flat_lines, inner_width);
+            let content_len = app.content_offset + row_counts.iter().sum::<usize>();
+            app.textarea = TextArea::from(wrapped_lines);
+            app.textarea.set_block(
+                ratatui::widgets::block::Block::default()
+                    .title(Span::styled(
+                        " 详情（Text Focus）",
+                        Style::default().fg(th.accent),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(th.muted)),
+            );
+            app.textarea.set_cursor_line_style(Style::default());
+            app.textarea
+                .set_cursor_style(Style::default().bg(app.theme.accent).fg(Color::Black));
+            app.textarea
+                .set_selection_style(Style::default().bg(app.theme.selection_bg));
+            app.detail_cache = Some(DetailCache {
+                generation: app.flat_lines_generation,
+                width: inner_width,
+                theme_index: app.theme_index,
+                row_counts,
+                content_len,
+            });
+        }
+        let row_counts = app.detail_cache.as_ref().unwrap().row_counts.clone();
+        apply_textarea_scroll(app, &row_counts, inner_width);
+        let content_len = app.detail_cache.as_ref().unwrap().content_len;
         f.render_widget(&app.textarea, area);
         draw_scrollbar(f, area, app.right_scroll, content_len);
         return;
@@ -2160,6 +3504,16 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
             app.right_scroll = max_top;
         }
     }
+    // 滚动条要按实际换行后的行数算，而不是用可视区高度去估算总长度；
+    // 在 `lines` 被 `Paragraph::new` 吃掉之前先量出来。
+    let detail_content_len = if matches!(app.focus, Focus::Text) {
+        None
+    } else {
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let flat: Vec<String> = lines.iter().map(line_to_plain_text).collect();
+        let (_, row_counts) = wrap_flat_lines(&flat, inner_width);
+        Some(row_counts.iter().sum::<usize>().max(1))
+    };
     let para = Paragraph::new(lines)
         .wrap(Wrap { trim: false })
         .block(
@@ -2173,13 +3527,16 @@ fn draw_detail(f: &mut Frame, area: Rect, app: &mut App) {
         )
         .scroll((app.right_scroll as u16, 0));
     f.render_widget(para, area);
-    // 绘制滚动条（非 Text Focus 情况）
-    if !matches!(app.focus, Focus::Text) {
-        let content_len = app.right_scroll + app.right_viewport + 1; // 近似
+    if let Some(content_len) = detail_content_len {
         draw_scrollbar(f, area, app.right_scroll, content_len);
     }
 }
 
+/// 把一行 `Line` 的所有 `Span` 拼回纯文本，供滚动条的精确行数计算使用。
+fn line_to_plain_text(line: &Line) -> String {
+    line.spans.iter().map(|s| s.content.as_ref()).collect()
+}
+
 fn apply_textarea_scroll(app: &mut App, row_counts: &[usize], maxw: usize) -> usize {
     let width = maxw.max(1);
     let vp = app.right_viewport.max(1);
@@ -2330,6 +3687,99 @@ fn wrap_flat_lines(lines: &[String], maxw: usize) -> (Vec<String>, Vec<usize>) {
     (wrapped, counts)
 }
 
+fn cloze_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{c\d+::.*?(?:::.*?)?\}\}").unwrap())
+}
+
+/// 给一行纯文本逐字符算高亮样式（不改动文本本身，只用来渲染）：挖空
+/// `{{cN::text}}`/`{{cN::text::hint}}` 整体用 `th.warn`，反引号包住的行内
+/// 代码用 `th.info`，`**粗体**`/`*斜体*` 对应 `Modifier`，行首的 `#`/`##`
+/// 标题标记用 `th.accent` 加粗。挖空范围内部不再展开其余标记，其余标记
+/// 按先到先得的左到右一遍扫描，不处理嵌套；标记不闭合时原样跳过。
+fn highlight_line_styles(line: &str, th: &Theme) -> Vec<Style> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut styles = vec![Style::default(); chars.len()];
+    let mut in_cloze = vec![false; chars.len()];
+
+    for m in cloze_regex().find_iter(line) {
+        let from = line[..m.start()].chars().count();
+        let to = line[..m.end()].chars().count();
+        let style = Style::default().fg(th.warn);
+        for i in from..to {
+            styles[i] = style;
+            in_cloze[i] = true;
+        }
+    }
+
+    let hashes = chars.iter().take(6).take_while(|&&c| c == '#').count();
+    if hashes > 0 && chars.get(hashes) == Some(&' ') && !in_cloze[0] {
+        let marker = Style::default().fg(th.accent).add_modifier(Modifier::BOLD);
+        for s in styles.iter_mut().take(hashes) {
+            *s = marker;
+        }
+    }
+
+    let mut i = 0usize;
+    while i < chars.len() {
+        if in_cloze[i] {
+            i += 1;
+            continue;
+        }
+        if chars[i] == '`' {
+            if let Some(end) = (i + 1..chars.len()).find(|&j| !in_cloze[j] && chars[j] == '`') {
+                let style = Style::default().fg(th.info);
+                for s in styles[i..=end].iter_mut() {
+                    *s = style;
+                }
+                i = end + 1;
+                continue;
+            }
+        } else if chars[i] == '*' && chars.get(i + 1) == Some(&'*') {
+            if let Some(end) = (i + 2..chars.len())
+                .find(|&j| !in_cloze[j] && chars[j] == '*' && chars.get(j + 1) == Some(&'*'))
+            {
+                let style = Style::default().add_modifier(Modifier::BOLD);
+                for s in styles[i..=end + 1].iter_mut() {
+                    *s = style;
+                }
+                i = end + 2;
+                continue;
+            }
+        } else if chars[i] == '*' {
+            if let Some(end) = (i + 1..chars.len()).find(|&j| !in_cloze[j] && chars[j] == '*') {
+                let style = Style::default().add_modifier(Modifier::ITALIC);
+                for s in styles[i..=end].iter_mut() {
+                    *s = style;
+                }
+                i = end + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    styles
+}
+
+/// 把 `[from, to)` 范围内连续同样式的字符合并成一个 `Span`。
+fn styled_spans_from_range(chars: &[char], styles: &[Style], from: usize, to: usize) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    if from >= to {
+        return spans;
+    }
+    let mut start = from;
+    for i in (from + 1)..=to {
+        if i == to || styles[i] != styles[start] {
+            spans.push(Span::styled(
+                chars[start..i].iter().collect::<String>(),
+                styles[start],
+            ));
+            start = i;
+        }
+    }
+    spans
+}
+
 fn render_flat_text(lines: &mut Vec<Line>, app: &App) {
     let th = app.theme;
     let n = app.flat_lines.len();
@@ -2347,10 +3797,10 @@ fn render_flat_text(lines: &mut Vec<Line>, app: &App) {
     };
     for i in 0..n {
         let s = &app.flat_lines[i];
-        // 统一在这里渲染：先按选择高亮，再在光标处覆盖纯色块
         let chars: Vec<char> = s.chars().collect();
         let len = chars.len();
-        let mut spans: Vec<Span> = Vec::new();
+        // 先按 cloze/Markdown 标记算出基础样式，选区/光标都在它上面叠加
+        let mut styles = highlight_line_styles(s, &th);
         // 计算当前行的选择范围
         let (sel_start, sel_end) = if let Some((sl, sc, el, ec)) = sel {
             if matches!(app.visual_kind, VisualKind::Line) {
@@ -2375,91 +3825,35 @@ fn render_flat_text(lines: &mut Vec<Line>, app: &App) {
         } else {
             (None, None)
         };
-
-        // 基础：未选中全部普通渲染
-        let mut idx = 0usize;
-        // 未选部分（左）
-        if let Some(ss) = sel_start {
-            if ss > 0 {
-                spans.push(Span::raw(chars[0..ss].iter().collect::<String>()));
-            }
-            idx = ss;
-        }
-        // 选中部分
+        // 选中范围叠加选区样式：bg 覆盖，原有 fg/modifier 保留
         if let Some(ss) = sel_start {
-            let ee = sel_end.unwrap_or(len);
-            if ee > ss {
-                spans.push(Span::styled(
-                    chars[ss..ee].iter().collect::<String>(),
-                    Style::default().bg(th.selection_bg),
-                ));
-                idx = ee;
+            let ee = sel_end.unwrap_or(len).min(len);
+            let sel_sty = selection_style(&th);
+            for st in styles[ss.min(len)..ee].iter_mut() {
+                *st = st.patch(sel_sty);
             }
         }
-        // 未选部分（右）
-        if idx < len {
-            spans.push(Span::raw(chars[idx..].iter().collect::<String>()));
-        }
 
         // 覆盖光标样式
         if i == app.cursor_line {
             if matches!(app.mode, Mode::Visual) {
                 let c = app.cursor_col.min(len);
-                // 保留选区高亮，同时在光标处插入纯色块
                 let mut new_line: Vec<Span> = Vec::new();
-                let ss = sel_start;
-                let ee = sel_end;
-                let build_range = |from: usize, to: usize| -> Vec<Span> {
-                    let mut out: Vec<Span> = Vec::new();
-                    if from >= to {
-                        return out;
-                    }
-                    if let Some(s) = ss {
-                        let e_use = ee.unwrap_or(len);
-                        if from < s {
-                            out.push(Span::raw(chars[from..s.min(to)].iter().collect::<String>()));
-                        }
-                        let sel_from = s.max(from);
-                        let sel_to = e_use.min(to);
-                        if sel_to > sel_from {
-                            out.push(Span::styled(
-                                chars[sel_from..sel_to].iter().collect::<String>(),
-                                Style::default().bg(th.selection_bg),
-                            ));
-                        }
-                        if to > e_use {
-                            out.push(Span::raw(
-                                chars[e_use.max(from)..to].iter().collect::<String>(),
-                            ));
-                        }
-                    } else {
-                        out.push(Span::raw(chars[from..to].iter().collect::<String>()));
-                    }
-                    out
-                };
-                // 左侧范围
-                new_line.extend(build_range(0, c));
-                // 光标块
-                new_line.push(Span::styled(
-                    "█",
-                    Style::default().fg(th.accent).bg(th.accent),
-                ));
-                // 右侧范围
-                new_line.extend(build_range(c, len));
+                new_line.extend(styled_spans_from_range(&chars, &styles, 0, c));
+                new_line.push(Span::styled("█", cursor_block_style(&th)));
+                new_line.extend(styled_spans_from_range(&chars, &styles, c, len));
                 lines.push(Line::from(new_line));
             } else {
                 // Normal 模式：细竖线
                 let a = app.cursor_col.min(len);
-                let left: String = chars[0..a].iter().collect();
-                let right: String = chars[a..].iter().collect();
-                lines.push(Line::from(vec![
-                    Span::raw(left),
-                    Span::styled("▏", Style::default().fg(th.accent)),
-                    Span::raw(right),
-                ]));
+                let mut new_line: Vec<Span> = Vec::new();
+                new_line.extend(styled_spans_from_range(&chars, &styles, 0, a));
+                new_line.push(Span::styled("▏", cursor_bar_style(&th)));
+                new_line.extend(styled_spans_from_range(&chars, &styles, a, len));
+                lines.push(Line::from(new_line));
             }
         } else {
-            lines.push(Line::from(spans));
+            lines.push(Line::from(styled_spans_from_range(&chars, &styles, 0, len)));
         }
     }
 }
@@ -2474,7 +3868,7 @@ fn push_split_line(buf: &mut Vec<Line>, s: &str, a: Option<usize>, b: Option<usi
         let right: String = chars[b..].iter().collect();
         buf.push(Line::from(vec![
             Span::raw(left),
-            Span::styled(mid, Style::default().bg(th.selection_bg)),
+            Span::styled(mid, selection_style(&th)),
             Span::raw(right),
         ]));
     } else if let (Some(aa), None) = (a, b) {
@@ -2484,7 +3878,7 @@ fn push_split_line(buf: &mut Vec<Line>, s: &str, a: Option<usize>, b: Option<usi
         let right: String = chars[a..].iter().collect();
         buf.push(Line::from(vec![
             Span::raw(left),
-            Span::styled(right, Style::default().bg(th.selection_bg)),
+            Span::styled(right, selection_style(&th)),
         ]));
     } else {
         buf.push(Line::from(Span::raw(s.to_string())));
@@ -2532,6 +3926,11 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
             format!("{}", if app.due_only { "ON" } else { "OFF" }),
             Style::default().fg(if app.due_only { th.good } else { th.muted }),
         ),
+        Span::styled(" | 语义:", Style::default().fg(th.muted)),
+        Span::styled(
+            if app.semantic_mode { "ON" } else { "OFF" },
+            Style::default().fg(if app.semantic_mode { th.good } else { th.muted }),
+        ),
         Span::styled(" | stats:", Style::default().fg(th.muted)),
         Span::styled(
             format!(" new:{} reviewing:{} mastered:{}", n, r, m),
@@ -2558,6 +3957,12 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
         segs.push(Span::styled(q, Style::default().fg(th.fg)));
         segs.push(Span::styled("_", Style::default().fg(th.accent)));
     }
+    if let Some(notice) = &app.reload_notice {
+        segs.push(Span::styled(
+            format!("  ⟳ {}", notice),
+            Style::default().fg(th.warn),
+        ));
+    }
     let text = Line::from(segs);
     let para = Paragraph::new(text).style(Style::default().bg(th.bar_bg).fg(th.fg));
     f.render_widget(para, area);
@@ -2573,6 +3978,16 @@ fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
     tips.push_str(" | Text: [v/V]Visual/Line  [y]复制  [Ctrl+S]保存笔记 ");
     tips.push_str(" | Questions/Notes: [/]搜索 [o]折叠 [Tab]切换  [S]Scraper ");
     tips.push_str(" | Flash: [F]进入/退出  [Space]揭示  [n/p]切换  [z/x/g/v]评分 ");
+    tips.push_str(" | [Z]语义检索  [T]切换主题 ");
+    if !app.keymap_pending.is_empty() {
+        let seq = app
+            .keymap_pending
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        tips.push_str(&format!(" | 等待组合键: {seq}… "));
+    }
     let help = Paragraph::new(Line::from(vec![Span::styled(
         tips,
         Style::default().fg(th.muted),
@@ -2607,7 +4022,7 @@ fn render_selectable(lines: &mut Vec<Line>, text: &str, app: &App, block_idx: us
             let right: String = chars[b..].iter().collect();
             buf.push(Line::from(vec![
                 Span::raw(left),
-                Span::styled(mid, Style::default().bg(th.selection_bg)),
+                Span::styled(mid, selection_style(&th)),
                 Span::raw(right),
             ]));
         } else {
@@ -2624,33 +4039,231 @@ fn render_selectable(lines: &mut Vec<Line>, text: &str, app: &App, block_idx: us
                 let right: String = chars[a..].iter().collect();
                 lines.push(Line::from(vec![
                     Span::raw(left),
-                    Span::styled("▏", Style::default().fg(th.accent)),
+                    Span::styled("▏", cursor_bar_style(&th)),
                     Span::raw(right),
                 ]));
             } else {
                 push_split(lines, text, Some(sc), Some(ec));
             }
-        } else if sl == line_idx && line_idx < el {
-            push_split(lines, text, Some(sc), None);
-        } else if el == line_idx && line_idx > sl {
-            push_split(lines, text, Some(0), Some(ec));
-        } else if line_idx > sl && line_idx < el {
-            push_split(lines, text, Some(0), None);
-        } else {
-            push_split(lines, text, None, None);
+        } else if sl == line_idx && line_idx < el {
+            push_split(lines, text, Some(sc), None);
+        } else if el == line_idx && line_idx > sl {
+            push_split(lines, text, Some(0), Some(ec));
+        } else if line_idx > sl && line_idx < el {
+            push_split(lines, text, Some(0), None);
+        } else {
+            push_split(lines, text, None, None);
+        }
+    } else {
+        push_split(lines, text, None, None);
+    }
+}
+
+// ---------------- Keymap ----------------
+// 支持具名键（`Esc`/`Space`/`Enter`/`Tab`/`Backspace`）、`Ctrl-` 前缀，以及用
+// 空格分隔的多键组合（如 `g g`）。事件循环里按键命中的是一棵 trie：每按一个
+// 键就把它追加进 `App::keymap_pending`，查不到就整串丢弃，查到前缀就继续等
+// 下一键，查到完整动作就触发并清空缓冲区。
+
+/// 组合键里的一个按键：具名键或裸字符，外加可选的 `Ctrl` 前缀。大小写字母
+/// 当作不同的裸字符处理（如 `a` 和 `A` 是两个键），`Ctrl-` 前缀则统一按键
+/// 本身加一个标志位表示，不再区分大小写的 Shift。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct KeyToken {
+    code: KeyTokenCode,
+    ctrl: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum KeyTokenCode {
+    Char(char),
+    Esc,
+    Enter,
+    Tab,
+    Backspace,
+}
+
+impl std::fmt::Display for KeyTokenCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyTokenCode::Char(' ') => write!(f, "Space"),
+            KeyTokenCode::Char(c) => write!(f, "{c}"),
+            KeyTokenCode::Esc => write!(f, "Esc"),
+            KeyTokenCode::Enter => write!(f, "Enter"),
+            KeyTokenCode::Tab => write!(f, "Tab"),
+            KeyTokenCode::Backspace => write!(f, "Backspace"),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl-{}", self.code)
+        } else {
+            write!(f, "{}", self.code)
+        }
+    }
+}
+
+impl KeyToken {
+    fn char(c: char) -> KeyToken {
+        KeyToken {
+            code: KeyTokenCode::Char(c),
+            ctrl: false,
+        }
+    }
+
+    /// 把实际按下的键翻译成 token。`Esc`/`Enter`/`Tab`/`Backspace` 在事件循环里
+    /// 各自还有写死的默认行为（取消搜索、切换面板等），调用方要先查一遍 keymap，
+    /// 查不到再退回默认行为，所以这里要覆盖到这几种具名键，而不只是 `Char`。
+    fn from_event(key: &KeyEvent) -> Option<KeyToken> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let code = match key.code {
+            KeyCode::Char(c) => KeyTokenCode::Char(c),
+            KeyCode::Esc => KeyTokenCode::Esc,
+            KeyCode::Enter => KeyTokenCode::Enter,
+            KeyCode::Tab => KeyTokenCode::Tab,
+            KeyCode::Backspace => KeyTokenCode::Backspace,
+            _ => return None,
+        };
+        Some(KeyToken { code, ctrl })
+    }
+}
+
+/// 解析单个按键名：`"a"`、`"A"`、`"Ctrl-s"`、`"Space"`、`"Esc"` 等。
+fn parse_key_token(spec: &str) -> Option<KeyToken> {
+    let spec = spec.trim();
+    let (ctrl, rest) = match spec.strip_prefix("Ctrl-").or_else(|| spec.strip_prefix("ctrl-")) {
+        Some(r) => (true, r),
+        None => (false, spec),
+    };
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => KeyTokenCode::Esc,
+        "space" => KeyTokenCode::Char(' '),
+        "enter" | "return" => KeyTokenCode::Enter,
+        "tab" => KeyTokenCode::Tab,
+        "backspace" | "bs" => KeyTokenCode::Backspace,
+        _ => {
+            let mut chars = rest.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None; // 既不是具名键也不是单字符，格式不认识
+            }
+            KeyTokenCode::Char(c)
         }
+    };
+    Some(KeyToken { code, ctrl })
+}
+
+/// 解析一整条组合键配置：按空格切成若干键，逐个解析；`"g g"` 就是两键组合。
+fn parse_key_sequence(spec: &str) -> Option<Vec<KeyToken>> {
+    let tokens = spec
+        .split_whitespace()
+        .map(parse_key_token)
+        .collect::<Option<Vec<_>>>()?;
+    if tokens.is_empty() {
+        None
     } else {
-        push_split(lines, text, None, None);
+        Some(tokens)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum KeymapNode {
+    Action(KeyAction),
+    Prefix(HashMap<KeyToken, KeymapNode>),
+}
+
+/// 按键组合 -> 动作的 trie；叶子是完整组合对应的 `KeyAction`，中间节点是还
+/// 没敲完的前缀。
+#[derive(Debug, Clone, Default)]
+struct Keymap {
+    root: HashMap<KeyToken, KeymapNode>,
+}
+
+enum KeymapLookup {
+    NoMatch,
+    Pending,
+    Action(KeyAction),
+}
+
+impl Keymap {
+    fn insert(&mut self, seq: &[KeyToken], action: KeyAction) {
+        Self::insert_into(&mut self.root, seq, action);
+    }
+
+    fn insert_into(map: &mut HashMap<KeyToken, KeymapNode>, seq: &[KeyToken], action: KeyAction) {
+        let Some((first, rest)) = seq.split_first() else {
+            return;
+        };
+        if rest.is_empty() {
+            map.insert(*first, KeymapNode::Action(action));
+            return;
+        }
+        let node = map
+            .entry(*first)
+            .or_insert_with(|| KeymapNode::Prefix(HashMap::new()));
+        if !matches!(node, KeymapNode::Prefix(_)) {
+            *node = KeymapNode::Prefix(HashMap::new());
+        }
+        if let KeymapNode::Prefix(next) = node {
+            Self::insert_into(next, rest, action);
+        }
+    }
+
+    /// 依次喂入目前已经按下的 token（含本次新按的），返回还在等待、没匹配到
+    /// 还是已经凑出一个完整动作。
+    fn lookup(&self, tokens: &[KeyToken]) -> KeymapLookup {
+        let mut map = &self.root;
+        for (i, tok) in tokens.iter().enumerate() {
+            match map.get(tok) {
+                None => return KeymapLookup::NoMatch,
+                Some(KeymapNode::Action(a)) => {
+                    return if i == tokens.len() - 1 {
+                        KeymapLookup::Action(*a)
+                    } else {
+                        KeymapLookup::NoMatch
+                    };
+                }
+                Some(KeymapNode::Prefix(next)) => map = next,
+            }
+        }
+        KeymapLookup::Pending
+    }
+}
+
+/// 把新按下的键并入 `app.keymap_pending`，按 trie 的匹配结果触发动作、继续
+/// 等待或者放弃整个组合。返回这个键有没有被 keymap 认领（匹配到动作或者还
+/// 在等组合的后续键都算认领了）；调用方据此决定要不要再跑自己的默认行为。
+fn dispatch_keymap_key(app: &mut App, key: &KeyEvent) -> Result<bool> {
+    let Some(tok) = KeyToken::from_event(key) else {
+        return Ok(false);
+    };
+    if app.keymap_pending.is_empty() {
+        app.keymap_pending_since = Some(Instant::now());
+    }
+    app.keymap_pending.push(tok);
+    match app.keymap.lookup(&app.keymap_pending) {
+        KeymapLookup::NoMatch => {
+            app.clear_keymap_pending();
+            Ok(false)
+        }
+        KeymapLookup::Pending => Ok(true),
+        KeymapLookup::Action(action) => {
+            app.clear_keymap_pending();
+            apply_action(app, action)?;
+            Ok(true)
+        }
     }
 }
 
-// ---------------- Keymap ----------------
 #[derive(Deserialize)]
 struct KeyMapToml {
     keys: HashMap<String, String>,
 }
 
-fn load_keymap() -> Result<HashMap<char, KeyAction>> {
+fn load_keymap() -> Result<Keymap> {
     // 探测 keymap.toml：当前目录及向上
     let mut paths = vec![PathBuf::from("keymap.toml")];
     if let Ok(cwd) = std::env::current_dir() {
@@ -2669,18 +4282,16 @@ fn load_keymap() -> Result<HashMap<char, KeyAction>> {
     Err(anyhow::anyhow!("未找到 keymap.toml"))
 }
 
-fn parse_keymap(map: HashMap<String, String>) -> HashMap<char, KeyAction> {
-    let mut out = HashMap::new();
+fn parse_keymap(map: HashMap<String, String>) -> Keymap {
+    let mut out = Keymap::default();
+    let mut any = false;
     for (k, v) in map {
-        if let Some(ch) = k.chars().next() {
-            if k.chars().count() == 1 {
-                if let Some(act) = action_from_str(&v) {
-                    out.insert(ch, act);
-                }
-            }
+        if let (Some(seq), Some(act)) = (parse_key_sequence(&k), action_from_str(&v)) {
+            out.insert(&seq, act);
+            any = true;
         }
     }
-    if out.is_empty() {
+    if !any {
         out = default_keymap();
     }
     out
@@ -2716,86 +4327,45 @@ fn action_from_str(s: &str) -> Option<KeyAction> {
         "yank_to_note" => YankToNote,
         "toggle_notes_fold" => ToggleNotesFold,
         "run_scraper" => RunScraper,
+        "toggle_semantic_search" => ToggleSemanticSearch,
+        "cycle_theme" => CycleTheme,
         _ => return None,
     })
 }
 
-fn default_keymap() -> HashMap<char, KeyAction> {
+fn default_keymap() -> Keymap {
     use KeyAction::*;
-    let mut m = HashMap::new();
-    m.insert('a', ToggleAnswerCurrent);
-    m.insert('A', ToggleAnswerGlobal);
-    m.insert('c', ToggleCommentsCurrent);
-    m.insert('C', ToggleCommentsGlobal);
-    m.insert('1', ToggleSourceSim);
-    m.insert('2', ToggleSourceReal);
-    m.insert('3', ToggleSourceFamous);
-    m.insert('n', MarkNew);
-    m.insert('r', MarkReviewing);
-    m.insert('m', MarkMastered);
-    m.insert('z', GradeAgain);
-    m.insert('x', GradeHard);
-    m.insert('g', GradeGood);
-    m.insert('v', GradeEasy);
-    m.insert('S', RunScraper); // 大写 S
-    m.insert('D', ToggleDueOnly); // 大写 D
-    m.insert('R', Reload); // 大写 R
-                           // Visual 默认
-    m.insert('v', VisualToggle);
-    m.insert('h', MoveLeft);
-    m.insert('l', MoveRight);
-    m.insert('j', MoveDownDetail);
-    m.insert('k', MoveUpDetail);
-    m.insert('y', YankToNote);
-    m.insert('o', ToggleNotesFold);
+    let mut m = Keymap::default();
+    let mut bind = |c: char, action: KeyAction| m.insert(&[KeyToken::char(c)], action);
+    bind('a', ToggleAnswerCurrent);
+    bind('A', ToggleAnswerGlobal);
+    bind('c', ToggleCommentsCurrent);
+    bind('C', ToggleCommentsGlobal);
+    bind('1', ToggleSourceSim);
+    bind('2', ToggleSourceReal);
+    bind('3', ToggleSourceFamous);
+    bind('n', MarkNew);
+    bind('r', MarkReviewing);
+    bind('m', MarkMastered);
+    bind('z', GradeAgain);
+    bind('x', GradeHard);
+    bind('g', GradeGood);
+    bind('v', GradeEasy);
+    bind('S', RunScraper); // 大写 S
+    bind('D', ToggleDueOnly); // 大写 D
+    bind('R', Reload); // 大写 R
+                        // Visual 默认
+    bind('v', VisualToggle);
+    bind('h', MoveLeft);
+    bind('l', MoveRight);
+    bind('j', MoveDownDetail);
+    bind('k', MoveUpDetail);
+    bind('y', YankToNote);
+    bind('o', ToggleNotesFold);
+    bind('Z', ToggleSemanticSearch);
+    bind('T', CycleTheme); // 大写 T：循环切换主题
     m
 }
-// ---------------- 主题与样式 ----------------
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum ThemeKind {
-    Dark,
-    Light,
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Theme {
-    // bg: Color, // 未使用，避免编译警告
-    fg: Color,
-    muted: Color,
-    accent: Color,
-    bar_bg: Color,
-    selection_bg: Color,
-    good: Color,
-    warn: Color,
-    info: Color,
-}
-
-fn theme_of(kind: ThemeKind) -> Theme {
-    match kind {
-        ThemeKind::Dark => Theme {
-            // bg: Color::Rgb(20, 22, 26),
-            fg: Color::Rgb(220, 220, 220),
-            muted: Color::Rgb(140, 140, 140),
-            accent: Color::Rgb(95, 175, 255), // 蓝色系，参考 yazi 风格
-            bar_bg: Color::Rgb(35, 40, 46),
-            selection_bg: Color::Rgb(60, 65, 72),
-            good: Color::Rgb(130, 200, 120),
-            warn: Color::Rgb(255, 200, 110),
-            info: Color::Rgb(120, 170, 255),
-        },
-        ThemeKind::Light => Theme {
-            // bg: Color::Rgb(250, 250, 250),
-            fg: Color::Rgb(30, 30, 30),
-            muted: Color::Rgb(120, 120, 120),
-            accent: Color::Rgb(0, 122, 255),
-            bar_bg: Color::Rgb(235, 240, 245),
-            selection_bg: Color::Rgb(210, 220, 235),
-            good: Color::Rgb(38, 166, 91),
-            warn: Color::Rgb(255, 160, 0),
-            info: Color::Rgb(0, 122, 255),
-        },
-    }
-}
 // ---------------- 笔记存储 ----------------
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct Note {
@@ -2821,6 +4391,10 @@ struct NotesFile {
     notes: Vec<Note>,
 }
 
+/// 笔记的内存视图；实际读写都经过 `App.store`（`Store::load_notes`/
+/// `save_notes`），这样 `--db` 一旦给出，笔记和题目落到同一个后端，不再
+/// 单独绕开 `Store` 直接读写 notes.json。`path` 只在 JSON 后端下有意义，
+/// 留着给文件监听比对路径用。
 #[derive(Debug)]
 struct NotesStore {
     path: PathBuf,
@@ -2828,29 +4402,25 @@ struct NotesStore {
 }
 
 impl NotesStore {
-    fn open(path: PathBuf) -> Result<Self> {
-        let data = if path.exists() {
-            let s = fs::read_to_string(&path)
-                .with_context(|| format!("读取笔记失败: {}", path.display()))?;
-            serde_json::from_str(&s).unwrap_or_default()
-        } else {
-            NotesFile::default()
-        };
+    fn open(store: &mut dyn Store, path: PathBuf) -> Result<Self> {
+        let mut data = store.load_notes()?;
+        backfill_note_tags(&mut data);
         Ok(Self { path, data })
     }
-    fn save(&self) -> Result<()> {
-        if let Some(dir) = self.path.parent() {
-            fs::create_dir_all(dir)?;
-        }
-        let s = serde_json::to_string_pretty(&self.data)?;
-        fs::write(&self.path, s)
-            .with_context(|| format!("写入笔记失败: {}", self.path.display()))?;
+    fn save(&self, store: &mut dyn Store) -> Result<()> {
+        store.save_notes(&self.data)
+    }
+    /// 重新载入，用于响应文件监听触发的外部变更。
+    fn reload(&mut self, store: &mut dyn Store) -> Result<()> {
+        self.data = store.load_notes()?;
+        backfill_note_tags(&mut self.data);
         Ok(())
     }
-    fn add_note(&mut self, qid: i64, excerpt: String, content: String) -> Result<()> {
+    fn add_note(&mut self, store: &mut dyn Store, qid: i64, excerpt: String, content: String) -> Result<()> {
         let now = Utc::now().to_rfc3339();
         let id = format!("n-{}-{}", qid, Utc::now().timestamp_millis());
         let title = derive_note_title(&excerpt, qid);
+        let tags = extract_tags(&content);
         let note = Note {
             id,
             qid,
@@ -2858,14 +4428,14 @@ impl NotesStore {
             parent_id: None,
             excerpt,
             content,
-            tags: vec![],
+            tags,
             created_at: now.clone(),
             updated_at: now,
             exam: None,
             exam_by_cloze: HashMap::new(),
         };
         self.data.notes.push(note);
-        self.save()
+        self.save(store)
     }
 }
 
@@ -2891,6 +4461,82 @@ fn note_display_title(note: &Note) -> String {
     }
 }
 
+/// 搜索 `#tag` 命中一个笔记时，往下带出多深的结构子树；太深会让一个标签
+/// 把半棵树都拖出来，失去筛选的意义，所以给个固定上限而不是无限展开。
+const TAG_FILTER_DESCENDANT_DEPTH: usize = 3;
+
+/// 从一段文本里解析出 `#tag` 标签：`#` 后面跟至少一个字母/数字/下划线/
+/// 连字符，但整段是纯数字的要排除——那是 `#123` 这种题目 qid 引用
+/// （见 `compute_completion`），不是标签。大小写不敏感，统一转小写存储，
+/// 按字典序去重排列。
+fn extract_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '#' {
+            let start = i + 1;
+            let mut j = start;
+            while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_' || chars[j] == '-') {
+                j += 1;
+            }
+            if j > start && !chars[start..j].iter().all(|c| c.is_ascii_digit()) {
+                tags.push(chars[start..j].iter().collect::<String>().to_lowercase());
+            }
+            i = j.max(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// 给加载进来的笔记补标签：`tags` 只在创建和编辑保存时写入，老数据（上线前
+/// 保存的 notes.json，或标签功能上线前就存在的笔记）一直是 `[]`，否则
+/// `#tag` 过滤会对这些笔记悄悄失效，直到用户碰巧重新保存一次。只补
+/// `tags` 为空的笔记，不覆盖用户手动清空标签的情形（目前没有“清空标签”的
+/// 操作入口，空等同于“从没算过”）。
+fn backfill_note_tags(data: &mut NotesFile) {
+    for note in &mut data.notes {
+        if note.tags.is_empty() {
+            note.tags = extract_tags(&note.content);
+        }
+    }
+}
+
+/// 把查询字符串里的 `#tag` 标签词摘掉，剩下的文字继续当作普通文本过滤条件
+/// （和 `extract_tags` 共用同一套 `#` 记号规则，纯数字的 `#123` 不算标签、
+/// 原样保留在剩余文本里）。
+fn strip_tag_tokens(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '#' {
+            let start = i + 1;
+            let mut j = start;
+            while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_' || chars[j] == '-') {
+                j += 1;
+            }
+            if j > start && !chars[start..j].iter().all(|c| c.is_ascii_digit()) {
+                i = j;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+fn note_contains_text(note: &Note, needle_lower: &str) -> bool {
+    note_display_title(note).to_lowercase().contains(needle_lower)
+        || note.content.to_lowercase().contains(needle_lower)
+        || note.excerpt.to_lowercase().contains(needle_lower)
+}
+
 fn note_excerpt_head(note: &Note) -> String {
     note.excerpt
         .lines()
@@ -2899,32 +4545,380 @@ fn note_excerpt_head(note: &Note) -> String {
         .unwrap_or_default()
 }
 
-fn note_matches_query(note: &Note, query: &str) -> bool {
+// ---------------- 模糊搜索（字符集预筛 + DP 子序列打分排序） ----------------
+
+/// 单个查询字符的基础命中分；落在词边界（见 `is_word_boundary`）额外加
+/// `FUZZY_BOUNDARY_BONUS`，相邻两个命中字符之间每跳过一个候选字符扣
+/// `FUZZY_GAP_PENALTY`。三者共同决定 `fuzzy_subsequence_match` 的打分。
+const FUZZY_CHAR_SCORE: i64 = 16;
+const FUZZY_BOUNDARY_BONUS: i64 = 10;
+const FUZZY_GAP_PENALTY: i64 = 2;
+/// 归一化后的最低分：低于这个分数的候选视为不匹配——目前等价于“完全凑不出
+/// 子序列”，留着这个常量是为了以后调严阈值时只改一处。
+const FUZZY_MIN_SCORE: f64 = 0.0;
+
+/// 把查询转成小写字符序列（去掉空白，空白只是视觉上的分词提示，不要求候选
+/// 文本里也有对应空格），并顺带算出它的字符集摘要，供调用方对每个候选复用。
+fn fuzzy_query_prepare(query: &str) -> (Vec<char>, u64) {
+    let chars: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let bag = chars.iter().fold(0u64, |acc, &c| acc | char_bag_bit(c));
+    (chars, bag)
+}
+
+fn char_bag_bit(c: char) -> u64 {
+    let hashed = (c as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    1u64 << (hashed >> 58)
+}
+
+/// 候选文本的字符集摘要：用来在跑 DP 之前快速淘汰明显不可能匹配的候选——
+/// 只要 query 用到的某个字符在候选里一个都没出现，子序列就必然凑不出来。
+/// bit 位置由字符哈希决定，存在碰撞也没关系，碰撞只会让过滤偏保守（多放几个
+/// 候选进 DP 复核），不会把真正能匹配的候选提前误杀。
+fn char_bag(s: &str) -> u64 {
+    s.to_lowercase()
+        .chars()
+        .fold(0u64, |acc, c| acc | char_bag_bit(c))
+}
+
+fn char_bag_is_superset(haystack_bag: u64, query_bag: u64) -> bool {
+    haystack_bag & query_bag == query_bag
+}
+
+/// 判断候选字符串里某个位置是否是“词边界”：串首，或者前一个字符是空白、
+/// `_`、`/`，或者常见中文标点——命中点落在词边界上通常意味着命中了一个词
+/// 的开头，应当比命中在词中间更值钱。
+fn is_word_boundary(chars: &[char], pos: usize) -> bool {
+    if pos == 0 {
+        return true;
+    }
+    matches!(
+        chars[pos - 1],
+        ' ' | '_'
+            | '/'
+            | '\n'
+            | '\t'
+            | '，'
+            | '。'
+            | '、'
+            | '：'
+            | '；'
+            | '！'
+            | '？'
+            | '（'
+            | '）'
+            | '【'
+            | '】'
+            | '《'
+            | '》'
+    )
+}
+
+/// query 作为子序列在 haystack 里的最优对齐：`row_score[j]`/`row_hits[j]`
+/// 始终表示“恰好用掉前 i 个 query 字符、只用候选的前 j 个字符”这一状态下
+/// 的最高分与命中位置（不可行记为 `i64::MIN`），按行滚动推进，避免真正开
+/// 一张 `m*n` 的二维表。`carry` 对应“第 i-1 个字符已经命中在某个位置之前”
+/// 这一最优起点，随着 j 右移按 `FUZZY_GAP_PENALTY` 衰减，模拟 gap 惩罚；
+/// 真正命中一个字符时再叠加 `FUZZY_CHAR_SCORE` 和词边界加分。全部 query
+/// 字符都有落点时返回归一化到 0..1 的分数和命中位置，否则返回 `None`。
+fn fuzzy_subsequence_match(haystack: &[char], haystack_lower: &[char], query: &[char]) -> Option<(f64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((1.0, Vec::new()));
+    }
+    let n = haystack_lower.len();
+    let m = query.len();
+    if n < m {
+        return None;
+    }
+
+    let mut prev_score = vec![0i64; n + 1];
+    let mut prev_hits: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+
+    for &qc in query {
+        let mut row_score = vec![i64::MIN; n + 1];
+        let mut row_hits: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+        let mut carry_val = i64::MIN;
+        let mut carry_hits: Vec<usize> = Vec::new();
+        for j in 1..=n {
+            carry_val = carry_val.saturating_sub(FUZZY_GAP_PENALTY);
+            if prev_score[j - 1] > carry_val {
+                carry_val = prev_score[j - 1];
+                carry_hits = prev_hits[j - 1].clone();
+            }
+            if carry_val != i64::MIN && haystack_lower[j - 1] == qc {
+                let boundary = if is_word_boundary(haystack, j - 1) {
+                    FUZZY_BOUNDARY_BONUS
+                } else {
+                    0
+                };
+                row_score[j] = carry_val + FUZZY_CHAR_SCORE + boundary;
+                let mut hits = carry_hits.clone();
+                hits.push(j - 1);
+                row_hits[j] = hits;
+            }
+            if row_score[j - 1] > row_score[j] {
+                row_score[j] = row_score[j - 1];
+                row_hits[j] = row_hits[j - 1].clone();
+            }
+        }
+        prev_score = row_score;
+        prev_hits = row_hits;
+    }
+
+    let final_score = prev_score[n];
+    if final_score == i64::MIN {
+        return None;
+    }
+    let max_possible = ((FUZZY_CHAR_SCORE + FUZZY_BOUNDARY_BONUS) * m as i64).max(1);
+    let normalized = (final_score.max(0) as f64 / max_possible as f64).min(1.0);
+    if normalized < FUZZY_MIN_SCORE {
+        return None;
+    }
+    Some((normalized, prev_hits[n].clone()))
+}
+
+/// 把字符数组逐字符转小写，强制保持和原数组一一对应的长度：标准
+/// `str::to_lowercase()` 对极少数字符（如土耳其语大写 `İ` U+0130）会展开成
+/// 多个字符，破坏 `fuzzy_subsequence_match` 依赖的“下标 j 在 haystack 和
+/// haystack_lower 里指向同一个字符”这一假设，导致用 `haystack_lower.len()`
+/// 算出的下标去取 `haystack` 时越界 panic。每个字符只取展开结果的第一个，
+/// 宁可在这类字符上损失大小写精度也不能让两个数组错位。
+fn lower_chars_same_len(chars: &[char]) -> Vec<char> {
+    chars
+        .iter()
+        .map(|&c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// 对一段候选文本做模糊匹配：先用字符集摘要快速淘汰，再跑 DP 子序列打分。
+/// `query`/`query_bag` 由调用方对整条查询算一次、所有候选复用。
+fn fuzzy_text_match(haystack: &str, query: &[char], query_bag: u64) -> Option<(f64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((1.0, Vec::new()));
+    }
+    if !char_bag_is_superset(char_bag(haystack), query_bag) {
+        return None;
+    }
+    let haystack_chars: Vec<char> = haystack.chars().collect();
+    let haystack_lower = lower_chars_same_len(&haystack_chars);
+    fuzzy_subsequence_match(&haystack_chars, &haystack_lower, query)
+}
+
+fn fuzzy_text_score(haystack: &str, query: &[char], query_bag: u64) -> Option<f64> {
+    fuzzy_text_match(haystack, query, query_bag).map(|(score, _)| score)
+}
+
+/// 笔记模糊匹配；整体打分仍然用标题+摘要+正文拼接的 haystack（和以前一样），
+/// 但额外对列表里实际展示的标题、摘要首行（`note_excerpt_head`）各自单独跑一遍
+/// 匹配，取其命中字符下标供高亮——单独匹配是因为展示的摘要只是 `note.excerpt`
+/// 掐头去尾后的首行，下标和完整 haystack 对不上；某一项单独匹配不到子序列时
+/// 该项就不高亮，不影响整体分数和是否进入结果。
+fn note_fuzzy_match(note: &Note, query: &[char], query_bag: u64) -> Option<(f64, Vec<usize>, Vec<usize>)> {
+    let title = note_display_title(note);
     let mut haystack = String::new();
-    haystack.push_str(&note_display_title(note));
+    haystack.push_str(&title);
     haystack.push('\n');
     haystack.push_str(&note.excerpt);
     haystack.push('\n');
     haystack.push_str(&note.content);
-    haystack.to_lowercase().contains(query)
+    let score = fuzzy_text_score(&haystack, query, query_bag)?;
+
+    let title_hits = fuzzy_text_match(&title, query, query_bag)
+        .map(|(_, pos)| pos)
+        .unwrap_or_default();
+    let excerpt_hits = fuzzy_text_match(&note_excerpt_head(note), query, query_bag)
+        .map(|(_, pos)| pos)
+        .unwrap_or_default();
+    Some((score, title_hits, excerpt_hits))
+}
+
+/// 供布尔/字段查询语言使用的字段表：`title:`/`content:` 各自独立，支持
+/// `title:"第一轮复习"` 这种把检索词收窄到笔记标题的写法。
+fn note_query_fields(note: &Note) -> query::QueryFields {
+    query::QueryFields::new(vec![
+        ("title", note_display_title(note)),
+        ("content", note.content.clone()),
+        ("excerpt", note.excerpt.clone()),
+    ])
+}
+
+const SEMANTIC_TOP_K: usize = 50;
+
+/// 题目送入 embeddings 接口的文本：题干 + 解析，足以覆盖“记得意思但记不清原文”的场景。
+fn question_embedding_text(q: &Question) -> String {
+    format!("{}\n{}", q.content, q.analysis)
+}
+
+/// 后台语义检索线程跑完后送回主线程的结果：`ranked` 是按相似度排好的行
+/// 下标（拿不到向量或请求失败时为 `None`，调用方据此回退到子串匹配），
+/// `new_entries` 是这一轮顺带算出来、需要合并回 `embedding_index` 持久化
+/// 缓存的新向量——后台线程不持有 `&mut App`，没法自己写缓存。
+struct SemanticJobResult {
+    ranked: Option<Vec<usize>>,
+    new_entries: Vec<(i64, semantic::EmbeddingEntry)>,
+}
+
+/// 正在后台跑的一次题目语义检索；`generation` 对应发起时的
+/// `app.question_search_generation`，结果回来时代数变了就说明用户已经
+/// 开始了新一轮搜索或取消了搜索，直接丢弃这次结果。
+struct SemanticJob {
+    generation: u64,
+    rx: mpsc::Receiver<SemanticJobResult>,
+}
+
+/// 把题目语义检索放到后台线程跑：embeddings 接口是阻塞 HTTP 调用，整个题库
+/// 没缓存过向量时要挨个打一遍请求，放主线程会冻住整个事件循环。主线程只
+/// 负责拍一份当前缓存和题目文本的快照传过去，线程算完再把新向量和排序
+/// 结果送回来，由 `poll_semantic_job` 在下一帧合并；没配置 embeddings 端点
+/// 时直接不启动任务。
+fn spawn_semantic_question_search(app: &mut App, query: String) {
+    let Some(client) = app.embedding_client.clone() else {
+        return;
+    };
+    let rows_text: Vec<(i64, String)> = app
+        .rows
+        .iter()
+        .map(|rr| {
+            let q = app.get_question(rr);
+            (q.id, question_embedding_text(q))
+        })
+        .collect();
+    // 只拷贝当前这批行用得到的缓存条目，而不是整份 embedding_index——题库大
+    // 的时候后者是一次性把所有题目的向量都深拷贝一遍，拍快照本身就会卡主线程。
+    let cached: HashMap<i64, semantic::EmbeddingEntry> = rows_text
+        .iter()
+        .filter_map(|(id, _)| {
+            app.embedding_index
+                .questions
+                .get(id)
+                .map(|entry| (*id, entry.clone()))
+        })
+        .collect();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = match client.embed(&query) {
+            Ok(query_vec) => {
+                let mut new_entries = Vec::new();
+                let mut candidates = Vec::with_capacity(rows_text.len());
+                for (i, (id, text)) in rows_text.into_iter().enumerate() {
+                    let hash = semantic::content_hash(&text);
+                    let vector = match cached.get(&id).filter(|e| e.content_hash == hash) {
+                        Some(entry) => Some(entry.vector.clone()),
+                        None => client.embed(&text).ok().inspect(|v| {
+                            new_entries.push((
+                                id,
+                                semantic::EmbeddingEntry {
+                                    content_hash: hash,
+                                    vector: v.clone(),
+                                },
+                            ));
+                        }),
+                    };
+                    if let Some(v) = vector {
+                        candidates.push((i, v));
+                    }
+                }
+                let ranked = (!candidates.is_empty())
+                    .then(|| semantic::rank_by_similarity(&query_vec, &candidates, SEMANTIC_TOP_K));
+                SemanticJobResult { ranked, new_entries }
+            }
+            Err(_) => SemanticJobResult {
+                ranked: None,
+                new_entries: Vec::new(),
+            },
+        };
+        let _ = tx.send(result);
+    });
+    app.semantic_job = Some(SemanticJob {
+        generation: app.question_search_generation,
+        rx,
+    });
+    app.reload_notice = Some("正在语义检索…".to_string());
+}
+
+/// 消费后台语义检索线程的结果；和 `poll_file_reload` 一样每帧调用一次，
+/// 没有在跑的任务或者结果还没到都直接返回。
+fn poll_semantic_job(app: &mut App) {
+    let Some(job) = app.semantic_job.as_ref() else {
+        return;
+    };
+    match job.rx.try_recv() {
+        Ok(result) => {
+            let generation = app.semantic_job.take().unwrap().generation;
+            for (id, entry) in result.new_entries {
+                app.embedding_index.questions.insert(id, entry);
+            }
+            let _ = app.embedding_index.save(&app.embeddings_path);
+            if generation == app.question_search_generation {
+                match result.ranked {
+                    Some(ranked) => {
+                        app.question_filtered_indices = ranked;
+                        if app.question_filtered_indices.is_empty() {
+                            app.list_state.select(None);
+                        } else {
+                            let sel = app
+                                .list_state
+                                .selected()
+                                .unwrap_or(0)
+                                .min(app.question_filtered_indices.len() - 1);
+                            app.list_state.select(Some(sel));
+                        }
+                        app.reload_notice = None;
+                    }
+                    None => app.reload_notice = Some("语义检索失败，已回退到普通搜索".to_string()),
+                }
+            }
+        }
+        Err(mpsc::TryRecvError::Empty) => {}
+        Err(mpsc::TryRecvError::Disconnected) => app.semantic_job = None,
+    }
 }
 
 fn refresh_question_filter(app: &mut App) {
     let mut indices: Vec<usize> = (0..app.rows.len()).collect();
+    app.question_search_highlights.clear();
     if app.question_search_active {
-        let query = app
+        let query_raw = app
             .question_search_query
-            .as_ref()
-            .map(|s| s.to_lowercase())
+            .clone()
             .unwrap_or_default();
-        if !query.is_empty() {
-            indices = app
-                .rows
-                .iter()
-                .enumerate()
-                .filter(|(_, rr)| question_matches(app, rr, &query))
-                .map(|(i, _)| i)
-                .collect();
+        if !query_raw.trim().is_empty() {
+            // 语义模式下不在这里发请求：真正的检索由 Enter 键触发的
+            // `spawn_semantic_question_search` 放到后台线程跑，这里敲字符时
+            // 只按子串/结构化查询先给一版即时反馈，不阻塞输入。
+            if let Some(node) = query::looks_structured(&query_raw)
+                .then(|| query::parse_query(&query_raw))
+                .flatten()
+            {
+                let mut scored: Vec<(usize, f64)> = (0..app.rows.len())
+                    .filter_map(|i| {
+                        let rr = app.rows[i].clone();
+                        let q = app.get_question(&rr);
+                        query::eval_score(&node, &question_query_fields(q)).map(|s| (i, s))
+                    })
+                    .collect();
+                scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                indices = scored.into_iter().map(|(i, _)| i).collect();
+            } else {
+                let (query, query_bag) = fuzzy_query_prepare(&query_raw);
+                let mut matches: Vec<(usize, f64, Vec<usize>)> = app
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, rr)| {
+                        let (score, hits) = question_fuzzy_match(app, rr, &query, query_bag)?;
+                        Some((i, score, hits))
+                    })
+                    .collect();
+                matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                indices = matches.iter().map(|m| m.0).collect();
+                for (i, _, hits) in matches {
+                    app.question_search_highlights.insert(i, hits);
+                }
+            }
         }
     }
     if indices.is_empty() {
@@ -2940,7 +4934,31 @@ fn refresh_question_filter(app: &mut App) {
     app.question_filtered_indices = indices;
 }
 
-fn question_matches(app: &App, rr: &RowRef, query: &str) -> bool {
+/// 供布尔/字段查询语言使用的字段表：`content`/`answer`/`analysis`/`comment`
+/// 各自独立，支持 `answer:"strict scrutiny"` 这种把检索词收窄到单个字段的写法。
+fn question_query_fields(q: &Question) -> query::QueryFields {
+    query::QueryFields::new(vec![
+        ("content", q.content.clone()),
+        ("answer", q.answer.join(" ")),
+        ("analysis", q.analysis.clone()),
+        ("comment", q.comments.join("\n")),
+    ])
+}
+
+/// 题目列表里展示的内容预览：题干首行，掐头去尾——和 `note_excerpt_head`
+/// 对笔记摘要的处理方式一致。
+fn question_content_preview(q: &Question) -> String {
+    q.content
+        .lines()
+        .next()
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// 题目模糊匹配；整体打分仍用题干+解析+答案+评论拼接的 haystack（和以前一样），
+/// 但额外对列表里实际展示的预览首行单独跑一遍匹配，取其命中字符下标供高亮——
+/// 原理同 `note_fuzzy_match`：预览只是题干的首行，下标和完整 haystack 对不上。
+fn question_fuzzy_match(app: &App, rr: &RowRef, query: &[char], query_bag: u64) -> Option<(f64, Vec<usize>)> {
     let q = app.get_question(rr);
     let mut hay = String::new();
     hay.push_str(&q.content);
@@ -2953,13 +4971,61 @@ fn question_matches(app: &App, rr: &RowRef, query: &str) -> bool {
         hay.push_str(comment);
         hay.push('\n');
     }
-    hay.to_lowercase().contains(query)
+    let score = fuzzy_text_score(&hay, query, query_bag)?;
+    let preview_hits = fuzzy_text_match(&question_content_preview(q), query, query_bag)
+        .map(|(_, pos)| pos)
+        .unwrap_or_default();
+    Some((score, preview_hits))
 }
 
 fn question_visible_count(app: &App) -> usize {
     app.question_filtered_indices.len()
 }
 
+/// 取（或按需计算并缓存）某条笔记的向量；没有配置 embeddings 端点或请求失败时返回 None。
+fn note_embedding(app: &mut App, idx: usize) -> Option<Vec<f32>> {
+    let (id, text) = {
+        let note = app.notes.data.notes.get(idx)?;
+        (
+            note.id.clone(),
+            format!("{}\n{}", note_display_title(note), note.content),
+        )
+    };
+    let hash = semantic::content_hash(&text);
+    if let Some(entry) = app.embedding_index.notes.get(&id) {
+        if entry.content_hash == hash {
+            return Some(entry.vector.clone());
+        }
+    }
+    let client = app.embedding_client.as_ref()?;
+    let vector = client.embed(&text).ok()?;
+    app.embedding_index.notes.insert(
+        id,
+        semantic::EmbeddingEntry {
+            content_hash: hash,
+            vector: vector.clone(),
+        },
+    );
+    Some(vector)
+}
+
+fn semantic_note_ranking(app: &mut App, query: &str) -> Option<Vec<usize>> {
+    let client = app.embedding_client.clone()?;
+    let query_vec = client.embed(query).ok()?;
+    let mut candidates = Vec::with_capacity(app.notes.data.notes.len());
+    for idx in 0..app.notes.data.notes.len() {
+        if let Some(v) = note_embedding(app, idx) {
+            candidates.push((idx, v));
+        }
+    }
+    if candidates.is_empty() {
+        return None;
+    }
+    let ranked = semantic::rank_by_similarity(&query_vec, &candidates, SEMANTIC_TOP_K);
+    let _ = app.embedding_index.save(&app.embeddings_path);
+    Some(ranked)
+}
+
 fn rebuild_note_view(app: &mut App) {
     let prev_indices = app.filtered_note_indices.clone();
     let prev_selected = app
@@ -2973,20 +5039,75 @@ fn rebuild_note_view(app: &mut App) {
         .map(|s| !s.is_empty())
         .unwrap_or(false);
 
+    app.note_search_highlights.clear();
     if has_query {
-        let query = app
-            .note_search_query
-            .as_ref()
-            .map(|s| s.to_lowercase())
-            .unwrap_or_default();
-        let mut indices = Vec::new();
-        for (idx, note) in app.notes.data.notes.iter().enumerate() {
-            if note_matches_query(note, &query) {
-                indices.push(idx);
+        let query_raw = app.note_search_query.clone().unwrap_or_default();
+        let semantic_ranked = if app.semantic_mode {
+            semantic_note_ranking(app, &query_raw)
+        } else {
+            None
+        };
+        let query_tags = extract_tags(&query_raw);
+        let is_tag_filter = !query_tags.is_empty();
+        let indices = if let Some(ranked) = semantic_ranked {
+            ranked
+        } else if is_tag_filter {
+            let leftover = strip_tag_tokens(&query_raw).trim().to_lowercase();
+            let roots: Vec<usize> = app
+                .notes
+                .data
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| query_tags.iter().any(|t| n.tags.contains(t)))
+                .filter(|(_, n)| leftover.is_empty() || note_contains_text(n, &leftover))
+                .map(|(idx, _)| idx)
+                .collect();
+            let (order, depths) =
+                build_tag_filtered_order(&app.notes.data.notes, &roots, TAG_FILTER_DESCENDANT_DEPTH);
+            app.note_indent_levels = depths;
+            order
+        } else if let Some(node) = query::looks_structured(&query_raw)
+            .then(|| query::parse_query(&query_raw))
+            .flatten()
+        {
+            let mut scored: Vec<(usize, f64)> = app
+                .notes
+                .data
+                .notes
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, note)| {
+                    query::eval_score(&node, &note_query_fields(note)).map(|s| (idx, s))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            scored.into_iter().map(|(idx, _)| idx).collect()
+        } else {
+            let (query, query_bag) = fuzzy_query_prepare(&query_raw);
+            let mut matches: Vec<(usize, f64, Vec<usize>, Vec<usize>)> = app
+                .notes
+                .data
+                .notes
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, note)| {
+                    let (score, title_hits, excerpt_hits) = note_fuzzy_match(note, &query, query_bag)?;
+                    Some((idx, score, title_hits, excerpt_hits))
+                })
+                .collect();
+            matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            let indices: Vec<usize> = matches.iter().map(|m| m.0).collect();
+            for (idx, _, title_hits, excerpt_hits) in matches {
+                app.note_search_highlights
+                    .insert(idx, (title_hits, excerpt_hits));
             }
-        }
+            indices
+        };
         app.filtered_note_indices = indices;
-        app.note_indent_levels = vec![0; app.filtered_note_indices.len()];
+        if !is_tag_filter {
+            app.note_indent_levels = vec![0; app.filtered_note_indices.len()];
+        }
     } else {
         let anchor_id = if matches!(app.note_fold_mode, NotesFoldMode::CurrentParent) {
             prev_selected
@@ -3068,6 +5189,7 @@ fn build_note_order(notes: &[Note], anchor: Option<&str>) -> (Vec<usize>, Vec<us
         expand_all,
         expanded_chain.as_ref(),
         &mut visited,
+        None,
     );
     for idx in 0..notes.len() {
         if visited.contains(&idx) {
@@ -3093,6 +5215,71 @@ fn build_note_order(notes: &[Note], anchor: Option<&str>) -> (Vec<usize>, Vec<us
                 expand_all,
                 expanded_chain.as_ref(),
                 &mut visited,
+                None,
+            );
+        }
+    }
+    (order, depths)
+}
+
+/// 按 `#tag` 命中的笔记为根，往下展开其结构子树（最深到 `max_depth` 层），
+/// 生成真实的 order/缩进而不是拍平成一条平行列表；复用 `dfs_notes`，只是
+/// 根集合换成了标签命中的笔记，而不是顶层（`parent_id` 为空）的笔记。
+fn build_tag_filtered_order(notes: &[Note], roots: &[usize], max_depth: usize) -> (Vec<usize>, Vec<usize>) {
+    let id_to_index: HashMap<String, usize> = notes
+        .iter()
+        .enumerate()
+        .map(|(idx, note)| (note.id.clone(), idx))
+        .collect();
+
+    let mut children: HashMap<Option<String>, Vec<usize>> = HashMap::new();
+    for (idx, note) in notes.iter().enumerate() {
+        let parent = note
+            .parent_id
+            .as_ref()
+            .filter(|pid| id_to_index.contains_key(pid.as_str()))
+            .cloned();
+        children.entry(parent).or_default().push(idx);
+    }
+    for vec in children.values_mut() {
+        vec.sort_by(|a, b| {
+            let a_key = note_display_title(&notes[*a]).to_lowercase();
+            let b_key = note_display_title(&notes[*b]).to_lowercase();
+            a_key
+                .cmp(&b_key)
+                .then_with(|| notes[*a].created_at.cmp(&notes[*b].created_at))
+        });
+    }
+
+    let mut sorted_roots = roots.to_vec();
+    sorted_roots.sort_by(|&a, &b| {
+        note_display_title(&notes[a])
+            .to_lowercase()
+            .cmp(&note_display_title(&notes[b]).to_lowercase())
+    });
+
+    let mut order = Vec::new();
+    let mut depths = Vec::new();
+    let mut visited = HashSet::new();
+    for idx in sorted_roots {
+        if !visited.insert(idx) {
+            continue;
+        }
+        order.push(idx);
+        depths.push(0);
+        if max_depth > 0 {
+            let id = notes[idx].id.clone();
+            dfs_notes(
+                Some(id),
+                1,
+                &children,
+                notes,
+                &mut order,
+                &mut depths,
+                true,
+                None,
+                &mut visited,
+                Some(max_depth),
             );
         }
     }
@@ -3109,7 +5296,11 @@ fn dfs_notes(
     expand_all: bool,
     expanded_chain: Option<&HashSet<String>>,
     visited: &mut HashSet<usize>,
+    max_depth: Option<usize>,
 ) {
+    if max_depth.map(|limit| depth > limit).unwrap_or(false) {
+        return;
+    }
     if let Some(list) = children.get(&parent) {
         for &idx in list {
             if !visited.insert(idx) {
@@ -3131,6 +5322,7 @@ fn dfs_notes(
                     expand_all,
                     expanded_chain,
                     visited,
+                    max_depth,
                 );
             }
         }
@@ -0,0 +1,450 @@
+// 小型布尔/字段检索查询语言：支持 `AND`/`OR`/`NOT`（含并列隐式 AND）、括号
+// 分组、`"引号短语"` 精确匹配，以及 `field:term`/`field:"phrase"` 把检索词
+// 收窄到某一个字段而不是拼接起来的整条 haystack。只有查询里用到了这些语法
+// 才值得解析成 AST 求值——纯裸词查询应该继续走 `main.rs` 里原有的模糊排序
+// 搜索，`looks_structured` 就是用来做这个判断的。
+//
+// 裸词/字段词还带打字容错：`TermMatcher` 按词长编译一次容错预算，子串精确
+// 命中记距离 0，否则在词元里找编辑距离最小的一个，`eval_score` 据此打分，
+// 让精确命中始终排在模糊命中前面；行首 `=` 或引号短语关掉容错只认精确匹配。
+
+/// 词法单元；`Field` 只携带冒号前的字段名，后面紧跟的词/短语由解析器继续
+/// 读下一个 token 拼起来。
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Field(String),
+    Phrase(String),
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+            continue;
+        }
+        if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+            continue;
+        }
+        if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                s.push(chars[i]);
+                i += 1;
+            }
+            i += 1; // 跳过闭合引号；没闭合就读到结尾，按已读到的内容处理
+            tokens.push(Token::Phrase(s));
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' && chars[i] != '"' {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        match word.as_str() {
+            "AND" => tokens.push(Token::And),
+            "OR" => tokens.push(Token::Or),
+            "NOT" => tokens.push(Token::Not),
+            _ => match word.split_once(':') {
+                Some((field, rest)) if !field.is_empty() && field.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                    tokens.push(Token::Field(field.to_string()));
+                    if !rest.is_empty() {
+                        tokens.push(Token::Word(rest.to_string()));
+                    }
+                    // `field:"phrase"` 中间没有空白，上面的裸词扫描会在 `"` 处停下，
+                    // 这里 rest 为空；紧接着主循环会在下一轮读到那段引号短语。
+                }
+                _ => tokens.push(Token::Word(word)),
+            },
+        }
+    }
+    tokens
+}
+
+/// 查询里是否用到了布尔/字段语法；为假时调用方应该把整条查询原样交给模糊
+/// 排序搜索，保持和没有这套语言之前完全一样的行为。
+pub fn looks_structured(input: &str) -> bool {
+    tokenize(input).iter().any(|t| !matches!(t, Token::Word(_)))
+}
+
+/// 解析后的查询语法树。
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryNode {
+    Term(String),
+    Phrase(String),
+    FieldTerm(String, Box<QueryNode>),
+    And(Box<QueryNode>, Box<QueryNode>),
+    Or(Box<QueryNode>, Box<QueryNode>),
+    Not(Box<QueryNode>),
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<&Token> {
+        let t = self.tokens.get(self.pos);
+        self.pos += 1;
+        t
+    }
+
+    fn parse_or(&mut self) -> Option<QueryNode> {
+        let mut node = self.parse_and()?;
+        while matches!(self.peek(), Some(Token::Or)) {
+            self.pos += 1;
+            let rhs = self.parse_and()?;
+            node = QueryNode::Or(Box::new(node), Box::new(rhs));
+        }
+        Some(node)
+    }
+
+    fn parse_and(&mut self) -> Option<QueryNode> {
+        let mut node = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.pos += 1;
+                    let rhs = self.parse_unary()?;
+                    node = QueryNode::And(Box::new(node), Box::new(rhs));
+                }
+                Some(Token::Or) | Some(Token::RParen) | None => break,
+                _ => {
+                    // 裸词/短语并列，不带显式 AND/OR，按隐式 AND 处理。
+                    let rhs = self.parse_unary()?;
+                    node = QueryNode::And(Box::new(node), Box::new(rhs));
+                }
+            }
+        }
+        Some(node)
+    }
+
+    fn parse_unary(&mut self) -> Option<QueryNode> {
+        if matches!(self.peek(), Some(Token::Not)) {
+            self.pos += 1;
+            let inner = self.parse_unary()?;
+            return Some(QueryNode::Not(Box::new(inner)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Option<QueryNode> {
+        match self.bump()?.clone() {
+            Token::LParen => {
+                let inner = self.parse_or()?;
+                if matches!(self.peek(), Some(Token::RParen)) {
+                    self.pos += 1;
+                }
+                Some(inner)
+            }
+            Token::Field(field) => {
+                let term = self.parse_term_or_phrase()?;
+                Some(QueryNode::FieldTerm(field, Box::new(term)))
+            }
+            Token::Phrase(p) => Some(QueryNode::Phrase(p)),
+            Token::Word(w) => Some(QueryNode::Term(w)),
+            Token::RParen | Token::And | Token::Or | Token::Not => None,
+        }
+    }
+
+    fn parse_term_or_phrase(&mut self) -> Option<QueryNode> {
+        match self.bump()?.clone() {
+            Token::Phrase(p) => Some(QueryNode::Phrase(p)),
+            Token::Word(w) => Some(QueryNode::Term(w)),
+            _ => None,
+        }
+    }
+}
+
+/// 把原始查询字符串解析成语法树；语法有误（括号不配对、操作符后面没有操作数
+/// 等）一律返回 `None`，调用方据此整体放弃结构化查询、退回模糊排序搜索。
+pub fn parse_query(input: &str) -> Option<QueryNode> {
+    let tokens = tokenize(input);
+    if tokens.is_empty() {
+        return None;
+    }
+    let mut parser = Parser { tokens: &tokens, pos: 0 };
+    let node = parser.parse_or()?;
+    if parser.pos != tokens.len() {
+        return None; // 还剩没消费完的 token，说明语法不完整
+    }
+    Some(node)
+}
+
+/// 一行题目/一条笔记里可被字段限定的文本片段，键名与 `field:` 前缀一一对应、
+/// 大小写不敏感查找。不带字段前缀的裸词/短语对所有字段一起做子串匹配，等价
+/// 于以前拼接整条 haystack 的做法。
+pub struct QueryFields(Vec<(&'static str, String)>);
+
+impl QueryFields {
+    pub fn new(fields: Vec<(&'static str, String)>) -> Self {
+        Self(fields)
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 在所有字段里找 `matcher` 的最佳命中，取编辑距离最小的那个。
+    fn best_match_anywhere(&self, matcher: &TermMatcher) -> Option<usize> {
+        self.0.iter().filter_map(|(_, v)| matcher.best_match(v)).min()
+    }
+}
+
+/// 一个编译好的查询词：子串精确命中记作编辑距离 0；否则按词长决定的预算
+/// （≤3 位精确、4-7 位容 1 处、更长容 2 处）在文本按空白切出的词元里找最近
+/// 的一个，编辑距离计算用带状剪枝提前退出，不必对长词元算满矩阵。词前面
+/// 带一个裸 `=` 或者本身来自引号短语时关掉容错，只认子串精确匹配。
+struct TermMatcher {
+    lower: String,
+    exact_only: bool,
+    max_dist: usize,
+}
+
+impl TermMatcher {
+    /// 编译一个裸词/字段词：识别开头的 `=` 并据此决定是否允许打字容错。
+    fn compile(term: &str) -> Self {
+        let exact_only = term.starts_with('=');
+        let word = if exact_only { &term[1..] } else { term };
+        let lower = word.to_lowercase();
+        let max_dist = if exact_only { 0 } else { typo_budget(lower.chars().count()) };
+        Self { lower, exact_only, max_dist }
+    }
+
+    /// 编译一个引号短语：来自 `"..."` 的词一律精确匹配，不参与打字容错。
+    fn exact(term: &str) -> Self {
+        Self { lower: term.to_lowercase(), exact_only: true, max_dist: 0 }
+    }
+
+    /// 返回命中这段文本所需的最小编辑距离；`None` 表示没有任何词元落在
+    /// 允许的预算内。子串精确命中（含空查询词）总是按距离 0 处理，
+    /// 只有它没命中、且本词允许容错时才去扫描按空白切出的词元。
+    fn best_match(&self, text: &str) -> Option<usize> {
+        let lower = text.to_lowercase();
+        if self.lower.is_empty() || lower.contains(&self.lower) {
+            return Some(0);
+        }
+        if self.exact_only {
+            return None;
+        }
+        lower
+            .split_whitespace()
+            .filter_map(|tok| bounded_levenshtein(tok, &self.lower, self.max_dist))
+            .min()
+    }
+}
+
+fn typo_budget(term_len: usize) -> usize {
+    match term_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// 按 `max_dist` 做带状剪枝的编辑距离：一旦某一行里所有值都已经超过
+/// `max_dist` 就提前判定两串不可能落在预算内，避免对长词元算满矩阵。
+fn bounded_levenshtein(a: &str, b: &str, max_dist: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_dist {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![0usize; b.len() + 1];
+        cur[0] = i;
+        let mut row_min = cur[0];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            row_min = row_min.min(cur[j]);
+        }
+        if row_min > max_dist {
+            return None;
+        }
+        prev = cur;
+    }
+    let dist = prev[b.len()];
+    (dist <= max_dist).then_some(dist)
+}
+
+/// 编辑距离换成排序用的分数：精确命中（距离 0）最高，每多容一处错就往下降，
+/// 让精确命中在结合相关性排序时总是排在模糊命中前面。
+fn match_score(dist: usize) -> f64 {
+    (1.0 - dist as f64 * 0.3).max(0.1)
+}
+
+/// 对查询语法树求值并打分：裸词/短语对所有字段找最佳命中，`field:` 限定的
+/// 词/短语只看对应字段；命中即 `Some(score)`，`None` 表示不匹配。`And` 取
+/// 两侧分数较低者（短板），`Or` 取较高者，`Not` 退化成纯布尔（取反后没有
+/// 连续分数可言，统一给满分）。
+pub fn eval_score(node: &QueryNode, fields: &QueryFields) -> Option<f64> {
+    match node {
+        QueryNode::Term(t) => fields.best_match_anywhere(&TermMatcher::compile(t)).map(match_score),
+        QueryNode::Phrase(p) => fields.best_match_anywhere(&TermMatcher::exact(p)).map(match_score),
+        QueryNode::FieldTerm(field, inner) => {
+            let matcher = match inner.as_ref() {
+                QueryNode::Term(t) => TermMatcher::compile(t),
+                QueryNode::Phrase(p) => TermMatcher::exact(p),
+                _ => return None,
+            };
+            fields
+                .field(field)
+                .and_then(|text| matcher.best_match(text))
+                .map(match_score)
+        }
+        QueryNode::And(a, b) => {
+            let sa = eval_score(a, fields)?;
+            let sb = eval_score(b, fields)?;
+            Some(sa.min(sb))
+        }
+        QueryNode::Or(a, b) => match (eval_score(a, fields), eval_score(b, fields)) {
+            (Some(sa), Some(sb)) => Some(sa.max(sb)),
+            (Some(s), None) | (None, Some(s)) => Some(s),
+            (None, None) => None,
+        },
+        QueryNode::Not(a) => {
+            if eval_score(a, fields).is_some() {
+                None
+            } else {
+                Some(1.0)
+            }
+        }
+    }
+}
+
+/// 对查询语法树求值：只看匹配与否，不需要分数时用这个。
+pub fn eval(node: &QueryNode, fields: &QueryFields) -> bool {
+    eval_score(node, fields).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_levenshtein_exact_match_is_zero() {
+        assert_eq!(bounded_levenshtein("abc", "abc", 2), Some(0));
+    }
+
+    #[test]
+    fn bounded_levenshtein_within_budget() {
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 3), Some(3));
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 2), None);
+    }
+
+    #[test]
+    fn bounded_levenshtein_length_diff_short_circuits() {
+        // 长度差已经超过预算，不用跑 DP 就能判定不可能命中。
+        assert_eq!(bounded_levenshtein("a", "abcd", 1), None);
+    }
+
+    #[test]
+    fn looks_structured_detects_boolean_and_field_syntax() {
+        assert!(!looks_structured("plain words here"));
+        assert!(looks_structured("foo AND bar"));
+        assert!(looks_structured("title:\"第一轮复习\""));
+        assert!(looks_structured("(a OR b)"));
+    }
+
+    #[test]
+    fn parse_query_implicit_and_between_bare_terms() {
+        let node = parse_query("foo bar").unwrap();
+        assert_eq!(
+            node,
+            QueryNode::And(
+                Box::new(QueryNode::Term("foo".into())),
+                Box::new(QueryNode::Term("bar".into())),
+            )
+        );
+    }
+
+    #[test]
+    fn parse_query_field_term_and_phrase() {
+        let node = parse_query("answer:\"strict scrutiny\"").unwrap();
+        assert_eq!(
+            node,
+            QueryNode::FieldTerm(
+                "answer".into(),
+                Box::new(QueryNode::Phrase("strict scrutiny".into())),
+            )
+        );
+    }
+
+    #[test]
+    fn parse_query_rejects_unbalanced_operator() {
+        // `AND` 后面没有操作数，语法不完整，应整体放弃结构化解析。
+        assert!(parse_query("foo AND").is_none());
+    }
+
+    #[test]
+    fn eval_and_takes_weaker_side_or_takes_stronger_side() {
+        let fields = QueryFields::new(vec![("content", "strict scrutiny applies".into())]);
+        let and_node = parse_query("strict AND missing").unwrap();
+        assert!(eval_score(&and_node, &fields).is_none());
+
+        let or_node = parse_query("missing OR strict").unwrap();
+        assert!(eval_score(&or_node, &fields).is_some());
+    }
+
+    #[test]
+    fn eval_not_inverts_match() {
+        let fields = QueryFields::new(vec![("content", "strict scrutiny applies".into())]);
+        let node = parse_query("NOT missing").unwrap();
+        assert!(eval(&node, &fields));
+        let node = parse_query("NOT strict").unwrap();
+        assert!(!eval(&node, &fields));
+    }
+
+    #[test]
+    fn eval_field_term_only_checks_named_field() {
+        let fields = QueryFields::new(vec![
+            ("content", "due process".into()),
+            ("answer", "strict scrutiny".into()),
+        ]);
+        let node = parse_query("content:scrutiny").unwrap();
+        assert!(!eval(&node, &fields));
+        let node = parse_query("answer:scrutiny").unwrap();
+        assert!(eval(&node, &fields));
+    }
+
+    #[test]
+    fn eval_term_tolerates_typos_within_budget() {
+        let fields = QueryFields::new(vec![("content", "jurisdiction over the matter".into())]);
+        let node = parse_query("jurisdicton").unwrap();
+        assert!(eval(&node, &fields));
+    }
+
+    #[test]
+    fn eval_exact_prefix_disables_typo_tolerance() {
+        let fields = QueryFields::new(vec![("content", "jurisdiction over the matter".into())]);
+        let node = parse_query("=jurisdicton").unwrap();
+        assert!(!eval(&node, &fields));
+    }
+}
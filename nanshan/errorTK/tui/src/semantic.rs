@@ -0,0 +1,111 @@
+// 语义检索：题目/笔记的本地向量索引。向量来自可配置的 OpenAI 兼容 `/embeddings`
+// 接口，按内容哈希缓存到侧车文件中避免重复计算；离线或请求失败时调用方应回退到
+// 普通子串匹配，这里不强依赖网络可用。
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EmbeddingIndex {
+    #[serde(default)]
+    pub questions: HashMap<i64, EmbeddingEntry>,
+    #[serde(default)]
+    pub notes: HashMap<String, EmbeddingEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingEntry {
+    pub content_hash: u64,
+    pub vector: Vec<f32>,
+}
+
+impl EmbeddingIndex {
+    pub fn open(path: &PathBuf) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let s = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, s).with_context(|| format!("写入向量索引失败: {}", path.display()))
+    }
+}
+
+pub fn content_hash(s: &str) -> u64 {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    s.hash(&mut h);
+    h.finish()
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingClient {
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+impl EmbeddingClient {
+    pub fn new(endpoint: String, api_key: Option<String>, model: String) -> Self {
+        Self {
+            endpoint,
+            api_key,
+            model,
+        }
+    }
+
+    /// 调用 OpenAI 兼容的 `/embeddings` 接口。失败（含离线）时返回 Err，
+    /// 调用方据此回退到子串匹配，不中断交互。
+    pub fn embed(&self, input: &str) -> Result<Vec<f32>> {
+        let mut req = ureq::post(&self.endpoint).set("Content-Type", "application/json");
+        if let Some(key) = &self.api_key {
+            req = req.set("Authorization", &format!("Bearer {key}"));
+        }
+        let body = serde_json::json!({ "model": self.model, "input": input });
+        let resp: serde_json::Value = req
+            .send_json(body)
+            .context("请求 embeddings 接口失败")?
+            .into_json()
+            .context("解析 embeddings 响应失败")?;
+        let vector = resp["data"][0]["embedding"]
+            .as_array()
+            .context("embeddings 响应缺少 data[0].embedding")?
+            .iter()
+            .filter_map(|v| v.as_f64().map(|f| f as f32))
+            .collect();
+        Ok(vector)
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let nb = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na * nb)
+    }
+}
+
+/// 按余弦相似度对 `(原始索引, 向量)` 排序，返回前 k 个原始索引。
+pub fn rank_by_similarity(query: &[f32], candidates: &[(usize, Vec<f32>)], k: usize) -> Vec<usize> {
+    let mut scored: Vec<(usize, f32)> = candidates
+        .iter()
+        .map(|(idx, v)| (*idx, cosine_similarity(query, v)))
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(k);
+    scored.into_iter().map(|(idx, _)| idx).collect()
+}
@@ -0,0 +1,508 @@
+// SQLite 存储后端：以 Store trait 抽象持久化，作为 errors.json 整文件重写的替代方案。
+// JsonStore 保留原有行为（兼容旧数据），SqliteStore 则把评分/状态变更落成定点
+// UPDATE/INSERT，避免每次复习都重写整份 JSON。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::{
+    default_exam_state, ErrorData, ExamState, Meta, Note, NotesFile, OptionItem, Question,
+    ReviewEvent, SourceKind,
+};
+
+/// `App` 的持久化出口：评分落盘、状态变更、整体加载/导出都经过这层，
+/// 这样 JSON 与 SQLite 两种后端可以互换而不触及调用方逻辑。笔记走同一层，
+/// 这样 `--db` 一旦给出，题目和笔记都落到同一个数据库而不是一半留在
+/// notes.json 里。
+pub trait Store: std::fmt::Debug {
+    fn load(&mut self) -> Result<ErrorData>;
+    fn save_all(&mut self, data: &ErrorData) -> Result<()>;
+    /// 单题落盘：JSON 后端没有定点更新的概念，退化为整文件重写；
+    /// SQLite 后端只对该题涉及的几张表做 UPDATE/INSERT。
+    fn save_question(&mut self, data: &ErrorData, source: SourceKind, q: &Question) -> Result<()>;
+    fn load_notes(&mut self) -> Result<NotesFile>;
+    /// 笔记整体落盘：JSON 后端整文件重写；SQLite 后端对 `notes` 表做 UPSERT，
+    /// 并清掉数据库里已经不存在于 `notes` 的行（对应笔记被删除的情况）。
+    fn save_notes(&mut self, notes: &NotesFile) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct JsonStore {
+    path: PathBuf,
+    notes_path: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf, notes_path: PathBuf) -> Self {
+        Self { path, notes_path }
+    }
+}
+
+impl Store for JsonStore {
+    fn load(&mut self) -> Result<ErrorData> {
+        crate::load_data(&self.path)
+    }
+
+    fn save_all(&mut self, data: &ErrorData) -> Result<()> {
+        crate::save_data(&self.path, data)
+    }
+
+    fn save_question(&mut self, data: &ErrorData, _source: SourceKind, _q: &Question) -> Result<()> {
+        self.save_all(data)
+    }
+
+    fn load_notes(&mut self) -> Result<NotesFile> {
+        if !self.notes_path.exists() {
+            return Ok(NotesFile::default());
+        }
+        let s = fs::read_to_string(&self.notes_path)
+            .with_context(|| format!("读取笔记失败: {}", self.notes_path.display()))?;
+        Ok(serde_json::from_str(&s).unwrap_or_default())
+    }
+
+    fn save_notes(&mut self, notes: &NotesFile) -> Result<()> {
+        if let Some(dir) = self.notes_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let s = serde_json::to_string_pretty(notes)?;
+        fs::write(&self.notes_path, s)
+            .with_context(|| format!("写入笔记失败: {}", self.notes_path.display()))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+// 形如 (version, sql) 的迁移列表；按顺序执行一次后记录到 schema_migrations。
+const MIGRATIONS: &[(i64, &str)] = &[(
+    1,
+    r#"
+    CREATE TABLE questions (
+        id INTEGER PRIMARY KEY,
+        source TEXT NOT NULL,
+        origin_name TEXT NOT NULL,
+        sub_name TEXT NOT NULL,
+        kind INTEGER NOT NULL DEFAULT 0,
+        content TEXT NOT NULL,
+        answer_json TEXT NOT NULL,
+        analysis TEXT NOT NULL,
+        comments_json TEXT NOT NULL,
+        user_status TEXT NOT NULL,
+        last_reviewed TEXT,
+        exam_by_cloze_json TEXT NOT NULL DEFAULT '{}'
+    );
+    CREATE TABLE options (
+        question_id INTEGER NOT NULL REFERENCES questions(id),
+        position INTEGER NOT NULL,
+        label TEXT NOT NULL,
+        content TEXT NOT NULL,
+        PRIMARY KEY (question_id, position)
+    );
+    CREATE TABLE exam_state (
+        question_id INTEGER PRIMARY KEY REFERENCES questions(id),
+        stage INTEGER NOT NULL,
+        again_streak INTEGER NOT NULL,
+        priority INTEGER NOT NULL,
+        due TEXT,
+        ease_factor REAL NOT NULL,
+        reps INTEGER NOT NULL,
+        interval_days REAL NOT NULL
+    );
+    CREATE TABLE review_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        question_id INTEGER NOT NULL REFERENCES questions(id),
+        ts TEXT NOT NULL,
+        grade TEXT NOT NULL
+    );
+    CREATE TABLE notes (
+        id TEXT PRIMARY KEY,
+        qid INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        parent_id TEXT,
+        excerpt TEXT NOT NULL,
+        content TEXT NOT NULL,
+        tags_json TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        exam_json TEXT,
+        exam_by_cloze_json TEXT NOT NULL DEFAULT '{}'
+    );
+    "#,
+)];
+
+fn run_migrations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY);",
+    )?;
+    for (version, sql) in MIGRATIONS {
+        let applied: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM schema_migrations WHERE version = ?1)",
+            params![version],
+            |row| row.get(0),
+        )?;
+        if applied {
+            continue;
+        }
+        conn.execute_batch(sql)
+            .with_context(|| format!("执行迁移 {version} 失败"))?;
+        conn.execute(
+            "INSERT INTO schema_migrations(version) VALUES (?1)",
+            params![version],
+        )?;
+    }
+    Ok(())
+}
+
+impl SqliteStore {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let conn = Connection::open(&path)
+            .with_context(|| format!("打开数据库失败: {}", path.display()))?;
+        run_migrations(&conn)?;
+        Ok(Self { conn })
+    }
+
+    fn upsert_question(&self, source: SourceKind, q: &Question) -> Result<()> {
+        let answer_json = serde_json::to_string(&q.answer)?;
+        let comments_json = serde_json::to_string(&q.comments)?;
+        let exam_by_cloze_json = serde_json::to_string(&q.exam_by_cloze)?;
+        self.conn.execute(
+            "INSERT INTO questions (id, source, origin_name, sub_name, kind, content, answer_json, analysis, comments_json, user_status, last_reviewed, exam_by_cloze_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(id) DO UPDATE SET
+                source = excluded.source, origin_name = excluded.origin_name, sub_name = excluded.sub_name,
+                kind = excluded.kind, content = excluded.content, answer_json = excluded.answer_json,
+                analysis = excluded.analysis, comments_json = excluded.comments_json,
+                user_status = excluded.user_status, last_reviewed = excluded.last_reviewed,
+                exam_by_cloze_json = excluded.exam_by_cloze_json",
+            params![
+                q.id,
+                source.as_str(),
+                q.origin_name,
+                q.sub_name,
+                q.r#type,
+                q.content,
+                answer_json,
+                q.analysis,
+                comments_json,
+                q.user_status,
+                q.last_reviewed,
+                exam_by_cloze_json,
+            ],
+        )?;
+
+        self.conn
+            .execute("DELETE FROM options WHERE question_id = ?1", params![q.id])?;
+        for (pos, o) in q.options.iter().enumerate() {
+            self.conn.execute(
+                "INSERT INTO options (question_id, position, label, content) VALUES (?1, ?2, ?3, ?4)",
+                params![q.id, pos as i64, o.label, o.content],
+            )?;
+        }
+
+        if let Some(ex) = &q.exam {
+            self.conn.execute(
+                "INSERT INTO exam_state (question_id, stage, again_streak, priority, due, ease_factor, reps, interval_days)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(question_id) DO UPDATE SET
+                    stage = excluded.stage, again_streak = excluded.again_streak, priority = excluded.priority,
+                    due = excluded.due, ease_factor = excluded.ease_factor, reps = excluded.reps,
+                    interval_days = excluded.interval_days",
+                params![
+                    q.id,
+                    ex.stage,
+                    ex.again_streak,
+                    ex.priority,
+                    ex.due,
+                    ex.ease_factor,
+                    ex.reps,
+                    ex.interval_days,
+                ],
+            )?;
+
+            // 历史只增量追加：仅写入尚未落盘的那部分事件。
+            let stored: i64 = self.conn.query_row(
+                "SELECT COUNT(*) FROM review_history WHERE question_id = ?1",
+                params![q.id],
+                |row| row.get(0),
+            )?;
+            for ev in ex.history.iter().skip(stored as usize) {
+                self.conn.execute(
+                    "INSERT INTO review_history (question_id, ts, grade) VALUES (?1, ?2, ?3)",
+                    params![q.id, ev.ts, ev.grade],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_question_row(
+        &self,
+        id: i64,
+        source: String,
+        origin_name: String,
+        sub_name: String,
+        kind: i32,
+        content: String,
+        answer_json: String,
+        analysis: String,
+        comments_json: String,
+        user_status: String,
+        last_reviewed: Option<String>,
+        exam_by_cloze_json: String,
+    ) -> Result<Question> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT label, content FROM options WHERE question_id = ?1 ORDER BY position")?;
+        let options = stmt
+            .query_map(params![id], |row| {
+                Ok(OptionItem {
+                    label: row.get(0)?,
+                    content: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let exam = self
+            .conn
+            .query_row(
+                "SELECT stage, again_streak, priority, due, ease_factor, reps, interval_days FROM exam_state WHERE question_id = ?1",
+                params![id],
+                |row| {
+                    Ok(ExamState {
+                        stage: row.get(0)?,
+                        again_streak: row.get(1)?,
+                        priority: row.get(2)?,
+                        due: row.get(3)?,
+                        history: vec![],
+                        ease_factor: row.get(4)?,
+                        reps: row.get(5)?,
+                        interval_days: row.get(6)?,
+                    })
+                },
+            )
+            .ok();
+        let mut exam = exam;
+        if let Some(ex) = exam.as_mut() {
+            let mut hstmt = self.conn.prepare_cached(
+                "SELECT ts, grade FROM review_history WHERE question_id = ?1 ORDER BY id",
+            )?;
+            ex.history = hstmt
+                .query_map(params![id], |row| {
+                    Ok(ReviewEvent {
+                        ts: row.get(0)?,
+                        grade: row.get(1)?,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+        }
+
+        Ok(Question {
+            id,
+            origin_name,
+            sub_name,
+            r#type: kind,
+            content,
+            options,
+            answer: serde_json::from_str(&answer_json).unwrap_or_default(),
+            analysis,
+            comments: serde_json::from_str(&comments_json).unwrap_or_default(),
+            user_status,
+            last_reviewed,
+            source: Some(source),
+            exam: exam.or_else(|| Some(default_exam_state())),
+            exam_by_cloze: serde_json::from_str(&exam_by_cloze_json).unwrap_or_default(),
+        })
+    }
+
+    fn upsert_note(&self, note: &Note) -> Result<()> {
+        let tags_json = serde_json::to_string(&note.tags)?;
+        let exam_json = match &note.exam {
+            Some(ex) => Some(serde_json::to_string(ex)?),
+            None => None,
+        };
+        let exam_by_cloze_json = serde_json::to_string(&note.exam_by_cloze)?;
+        self.conn.execute(
+            "INSERT INTO notes (id, qid, title, parent_id, excerpt, content, tags_json, created_at, updated_at, exam_json, exam_by_cloze_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(id) DO UPDATE SET
+                qid = excluded.qid, title = excluded.title, parent_id = excluded.parent_id,
+                excerpt = excluded.excerpt, content = excluded.content, tags_json = excluded.tags_json,
+                created_at = excluded.created_at, updated_at = excluded.updated_at,
+                exam_json = excluded.exam_json, exam_by_cloze_json = excluded.exam_by_cloze_json",
+            params![
+                note.id,
+                note.qid,
+                note.title,
+                note.parent_id,
+                note.excerpt,
+                note.content,
+                tags_json,
+                note.created_at,
+                note.updated_at,
+                exam_json,
+                exam_by_cloze_json,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load_notes_all(&self) -> Result<NotesFile> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, qid, title, parent_id, excerpt, content, tags_json, created_at, updated_at, exam_json, exam_by_cloze_json
+             FROM notes ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, Option<String>>(9)?,
+                row.get::<_, String>(10)?,
+            ))
+        })?;
+        let mut notes = Vec::new();
+        for row in rows {
+            let (id, qid, title, parent_id, excerpt, content, tags_json, created_at, updated_at, exam_json, exam_by_cloze_json) = row?;
+            notes.push(Note {
+                id,
+                qid,
+                title,
+                parent_id,
+                excerpt,
+                content,
+                tags: serde_json::from_str(&tags_json).unwrap_or_default(),
+                created_at,
+                updated_at,
+                exam: exam_json.and_then(|s| serde_json::from_str(&s).ok()),
+                exam_by_cloze: serde_json::from_str(&exam_by_cloze_json).unwrap_or_default(),
+            });
+        }
+        Ok(NotesFile { notes })
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&mut self) -> Result<ErrorData> {
+        let mut data = ErrorData {
+            meta: Meta::default(),
+            simulation: vec![],
+            real: vec![],
+            famous: vec![],
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT id, source, origin_name, sub_name, kind, content, answer_json, analysis, comments_json, user_status, last_reviewed, exam_by_cloze_json
+             FROM questions ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i32>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, Option<String>>(10)?,
+                row.get::<_, String>(11)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, source, origin_name, sub_name, kind, content, answer_json, analysis, comments_json, user_status, last_reviewed, exam_by_cloze_json) = row?;
+            let src = source.clone();
+            let q = self.load_question_row(
+                id,
+                source,
+                origin_name,
+                sub_name,
+                kind,
+                content,
+                answer_json,
+                analysis,
+                comments_json,
+                user_status,
+                last_reviewed,
+                exam_by_cloze_json,
+            )?;
+            match src.as_str() {
+                "real" => data.real.push(q),
+                "famous" => data.famous.push(q),
+                _ => data.simulation.push(q),
+            }
+        }
+        Ok(data)
+    }
+
+    fn save_all(&mut self, data: &ErrorData) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for q in &data.simulation {
+            self.upsert_question(SourceKind::Simulation, q)?;
+        }
+        for q in &data.real {
+            self.upsert_question(SourceKind::Real, q)?;
+        }
+        for q in &data.famous {
+            self.upsert_question(SourceKind::Famous, q)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn save_question(&mut self, _data: &ErrorData, source: SourceKind, q: &Question) -> Result<()> {
+        // `upsert_question` 内部是好几条独立的自动提交语句（先 DELETE options
+        // 再逐条重新 INSERT），评分这条热路径崩在中间会永久丢掉该题的选项，
+        // 所以和 `save_all`/`save_notes` 一样包一层事务，保证要么全写成功要么
+        // 整体回滚。
+        let tx = self.conn.unchecked_transaction()?;
+        self.upsert_question(source, q)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_notes(&mut self) -> Result<NotesFile> {
+        self.load_notes_all()
+    }
+
+    fn save_notes(&mut self, notes: &NotesFile) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for note in &notes.notes {
+            self.upsert_note(note)?;
+        }
+        let keep: Vec<String> = notes.notes.iter().map(|n| n.id.clone()).collect();
+        let placeholders = keep.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        if keep.is_empty() {
+            self.conn.execute("DELETE FROM notes", [])?;
+        } else {
+            let sql = format!("DELETE FROM notes WHERE id NOT IN ({placeholders})");
+            self.conn.execute(&sql, params_from_iter(keep.iter()))?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// 一次性迁移入口：读取现有 errors.json 并写入 SQLite 数据库。
+pub fn import_json(db_path: &Path, json_path: &Path) -> Result<()> {
+    let data = crate::load_data(&json_path.to_path_buf())?;
+    let mut store = SqliteStore::open(db_path.to_path_buf())?;
+    store.save_all(&data)
+}
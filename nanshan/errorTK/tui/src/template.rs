@@ -0,0 +1,219 @@
+// 列表行 / 闪卡展示模板：默认走内置布局，若探测到
+// `errorTK/tui/templates.toml` 则用 Handlebars 按用户自定义模板渲染，
+// 模板缺失字段或解析失败时逐项退回内置模板，保证界面总能渲染出东西。
+//
+// 模板里可以用 `{{pad width text}}`、`{{truncate width text}}` 控制宽度，
+// 用 `{{color "accent" text}}` 给一段文字上色（颜色名对应 `Theme` 的字段），
+// 渲染结果里的颜色标记由 `main.rs` 的 `spans_from_template` 解析回 `Span`。
+
+use std::path::PathBuf;
+
+use handlebars::{Handlebars, RenderErrorReason};
+use serde::Serialize;
+
+pub const COLOR_START: char = '\u{1}';
+pub const COLOR_SEP: char = '\u{2}';
+pub const COLOR_END: char = '\u{3}';
+
+const DEFAULT_LIST_ROW: &str =
+    "{{icon}} {{pad 6 id}} {{color \"accent\" source}} | {{origin}} - {{sub}}  {{color status_color status}}{{#if multi}}  {{color \"warn\" \"【多选题】\"}}{{/if}}";
+
+const DEFAULT_FLASH_QUESTION_HEADER: &str = "qid:{{id}} {{label}} · {{answered}}/{{total}}";
+
+const DEFAULT_FLASH_NOTE_HEADER: &str = "{{title}} · {{cloze}} ({{pos}}/{{count}})";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListRowContext {
+    pub icon: String,
+    pub id: String,
+    pub source: String,
+    pub origin: String,
+    pub sub: String,
+    pub status: String,
+    /// 对应 `Theme` 字段名（"good"/"warn"/"muted"），供 `color` 帮助函数查表。
+    pub status_color: String,
+    pub multi: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashQuestionContext {
+    pub id: i64,
+    pub label: String,
+    pub answered: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashNoteContext {
+    pub title: String,
+    pub cloze: String,
+    pub pos: usize,
+    pub count: usize,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct TemplateToml {
+    list_row: Option<String>,
+    flash_question_header: Option<String>,
+    flash_note_header: Option<String>,
+}
+
+pub struct Templates {
+    hb: Handlebars<'static>,
+    list_row: String,
+    flash_question_header: String,
+    flash_note_header: String,
+}
+
+impl Templates {
+    pub fn render_list_row(&self, ctx: &ListRowContext) -> String {
+        self.hb
+            .render_template(&self.list_row, ctx)
+            .unwrap_or_else(|_| self.hb.render_template(DEFAULT_LIST_ROW, ctx).unwrap_or_default())
+    }
+
+    pub fn render_flash_question_header(&self, ctx: &FlashQuestionContext) -> String {
+        self.hb
+            .render_template(&self.flash_question_header, ctx)
+            .unwrap_or_else(|_| {
+                self.hb
+                    .render_template(DEFAULT_FLASH_QUESTION_HEADER, ctx)
+                    .unwrap_or_default()
+            })
+    }
+
+    pub fn render_flash_note_header(&self, ctx: &FlashNoteContext) -> String {
+        self.hb
+            .render_template(&self.flash_note_header, ctx)
+            .unwrap_or_else(|_| {
+                self.hb
+                    .render_template(DEFAULT_FLASH_NOTE_HEADER, ctx)
+                    .unwrap_or_default()
+            })
+    }
+}
+
+fn pad_helper(
+    h: &handlebars::Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let width = h
+        .param(0)
+        .and_then(|v| v.value().as_u64())
+        .ok_or_else(|| RenderErrorReason::ParamNotFoundForIndex("pad", 0))? as usize;
+    let text = param_as_str(h, 1)?;
+    out.write(&format!("{:<width$}", text, width = width))?;
+    Ok(())
+}
+
+fn truncate_helper(
+    h: &handlebars::Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let width = h
+        .param(0)
+        .and_then(|v| v.value().as_u64())
+        .ok_or_else(|| RenderErrorReason::ParamNotFoundForIndex("truncate", 0))? as usize;
+    let text = param_as_str(h, 1)?;
+    if text.chars().count() <= width {
+        out.write(text)?;
+    } else {
+        let truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+        out.write(&truncated)?;
+        out.write("…")?;
+    }
+    Ok(())
+}
+
+/// 把一段文字包进颜色标记里；真正的上色发生在 `main.rs` 的
+/// `spans_from_template`，它按 `Theme` 的字段名把标记区间转成对应颜色的 `Span`。
+fn color_helper(
+    h: &handlebars::Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let color_name = param_as_str(h, 0)?;
+    let text = param_as_str(h, 1)?;
+    out.write(&format!(
+        "{COLOR_START}{color_name}{COLOR_SEP}{text}{COLOR_END}"
+    ))?;
+    Ok(())
+}
+
+fn param_as_str<'a>(h: &'a handlebars::Helper, idx: usize) -> Result<&'a str, handlebars::RenderError> {
+    h.param(idx)
+        .and_then(|v| v.value().as_str())
+        .ok_or_else(|| RenderErrorReason::ParamTypeMismatchForName("color", idx.to_string(), "string".into()).into())
+}
+
+fn registry() -> Handlebars<'static> {
+    let mut hb = Handlebars::new();
+    hb.set_strict_mode(false);
+    hb.register_helper("pad", Box::new(pad_helper));
+    hb.register_helper("truncate", Box::new(truncate_helper));
+    hb.register_helper("color", Box::new(color_helper));
+    hb
+}
+
+fn find_templates_toml() -> Option<PathBuf> {
+    let mut candidates = vec![PathBuf::from("templates.toml")];
+    if let Ok(cwd) = std::env::current_dir() {
+        for anc in cwd.ancestors() {
+            candidates.push(anc.join("errorTK/tui/templates.toml"));
+        }
+    }
+    candidates.into_iter().find(|p| p.exists())
+}
+
+/// 加载用户模板；没有配置文件、解析失败或缺字段都不是致命错误，每个模板
+/// 独立退回内置默认值。
+pub fn load_templates() -> Templates {
+    let user = find_templates_toml()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| toml::from_str::<TemplateToml>(&s).ok())
+        .unwrap_or_default();
+    Templates {
+        hb: registry(),
+        list_row: user.list_row.unwrap_or_else(|| DEFAULT_LIST_ROW.to_string()),
+        flash_question_header: user
+            .flash_question_header
+            .unwrap_or_else(|| DEFAULT_FLASH_QUESTION_HEADER.to_string()),
+        flash_note_header: user
+            .flash_note_header
+            .unwrap_or_else(|| DEFAULT_FLASH_NOTE_HEADER.to_string()),
+    }
+}
+
+/// 把模板渲染结果里 `color_helper` 留下的标记切回纯文本，丢弃颜色信息；
+/// 供不需要着色（如只取字符串长度）的调用方使用。
+pub fn strip_color_marks(rendered: &str) -> String {
+    let mut out = String::with_capacity(rendered.len());
+    let mut chars = rendered.chars();
+    while let Some(c) = chars.next() {
+        if c == COLOR_START {
+            for c2 in chars.by_ref() {
+                if c2 == COLOR_SEP {
+                    break;
+                }
+            }
+            for c2 in chars.by_ref() {
+                if c2 == COLOR_END {
+                    break;
+                }
+                out.push(c2);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
@@ -0,0 +1,245 @@
+// 主题子系统：内置 dark/light/high-contrast 三套基础配色，外加可选的
+// `errorTK/tui/themes/*.toml` 目录里用户自定义主题（支持 `extends` 继承
+// 某个已注册主题，只覆盖自己关心的字段）。运行时可通过按键循环切换，
+// 设置了 `NO_COLOR` 或传入 `--no-color` 时整体收敛成终端默认色，选区/
+// 光标改用 `Modifier` 表达，不再输出任何颜色转义。
+
+use std::{fs, path::PathBuf};
+
+use clap::ValueEnum;
+use ratatui::style::Color;
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThemeKind {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub fg: Color,
+    pub muted: Color,
+    pub accent: Color,
+    pub bar_bg: Color,
+    pub selection_bg: Color,
+    pub good: Color,
+    pub warn: Color,
+    pub info: Color,
+    /// `NO_COLOR` 或 `--no-color` 生效时置位；渲染侧据此把选区/光标改用
+    /// `Modifier`（反显、粗体、下划线）表达，不再依赖颜色填充。
+    pub mono: bool,
+}
+
+/// 一个已校验的十六进制颜色：`"#RRGGBB"` 或带透明度的 `"#RRGGBBAA"`
+/// （透明度被忽略，按不透明处理——ratatui 的 `Color` 本身不支持 alpha）。
+/// 反序列化阶段就地校验，格式不对会直接报出具体错误而不是悄悄吞掉。
+#[derive(Debug, Clone, Copy)]
+struct HexColor(Color);
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        parse_hex(&raw).map(HexColor).ok_or_else(|| {
+            de::Error::custom(format!(
+                "无效的十六进制颜色 `{raw}`：应为 6 位 RGB 或 8 位 RGBA 十六进制数字，形如 `#5FAFFF`"
+            ))
+        })
+    }
+}
+
+/// 从 TOML 读入的主题定义：每个颜色字段都是可选的十六进制字符串（如
+/// `"#5fafff"` 或 `"#5fafffaa"`），缺失的字段由 `extends` 指定的基础主题补齐。
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ThemeSpec {
+    pub name: Option<String>,
+    pub extends: Option<String>,
+    fg: Option<HexColor>,
+    muted: Option<HexColor>,
+    accent: Option<HexColor>,
+    bar_bg: Option<HexColor>,
+    selection_bg: Option<HexColor>,
+    good: Option<HexColor>,
+    warn: Option<HexColor>,
+    info: Option<HexColor>,
+}
+
+impl ThemeSpec {
+    /// 用 `base` 补齐本 spec 里没有覆盖的字段，生成一个完整的 `Theme`。
+    fn merge_onto(&self, base: Theme) -> Theme {
+        Theme {
+            fg: self.fg.map(|c| c.0).unwrap_or(base.fg),
+            muted: self.muted.map(|c| c.0).unwrap_or(base.muted),
+            accent: self.accent.map(|c| c.0).unwrap_or(base.accent),
+            bar_bg: self.bar_bg.map(|c| c.0).unwrap_or(base.bar_bg),
+            selection_bg: self.selection_bg.map(|c| c.0).unwrap_or(base.selection_bg),
+            good: self.good.map(|c| c.0).unwrap_or(base.good),
+            warn: self.warn.map(|c| c.0).unwrap_or(base.warn),
+            info: self.info.map(|c| c.0).unwrap_or(base.info),
+            mono: base.mono,
+        }
+    }
+}
+
+/// 支持 6 位 RGB（`#RRGGBB`）和 8 位 RGBA（`#RRGGBBAA`，透明度被忽略）。
+fn parse_hex(s: &str) -> Option<Color> {
+    let s = s.trim().trim_start_matches('#');
+    // 先确认整串都是 ASCII，再按字节长度判断位数；否则像 "123€"
+    // 这种总字节数凑巧等于 6/8、但含多字节字符的输入，会在下面按固定
+    // 字节偏移切片时越过字符边界直接 panic，而不是走到下面的报错分支。
+    if !s.is_ascii() || (s.len() != 6 && s.len() != 8) {
+        return None;
+    }
+    let r = u8::from_str_radix(&s[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&s[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&s[4..6], 16).ok()?;
+    Some(Color::Rgb(r, g, b))
+}
+
+fn builtin_themes() -> Vec<(String, Theme)> {
+    vec![
+        (
+            "dark".to_string(),
+            Theme {
+                fg: Color::Rgb(220, 220, 220),
+                muted: Color::Rgb(140, 140, 140),
+                accent: Color::Rgb(95, 175, 255), // 蓝色系，参考 yazi 风格
+                bar_bg: Color::Rgb(35, 40, 46),
+                selection_bg: Color::Rgb(60, 65, 72),
+                good: Color::Rgb(130, 200, 120),
+                warn: Color::Rgb(255, 200, 110),
+                info: Color::Rgb(120, 170, 255),
+                mono: false,
+            },
+        ),
+        (
+            "light".to_string(),
+            Theme {
+                fg: Color::Rgb(30, 30, 30),
+                muted: Color::Rgb(120, 120, 120),
+                accent: Color::Rgb(0, 122, 255),
+                bar_bg: Color::Rgb(235, 240, 245),
+                selection_bg: Color::Rgb(210, 220, 235),
+                good: Color::Rgb(38, 166, 91),
+                warn: Color::Rgb(255, 160, 0),
+                info: Color::Rgb(0, 122, 255),
+                mono: false,
+            },
+        ),
+        (
+            "high-contrast".to_string(),
+            Theme {
+                fg: Color::Rgb(255, 255, 255),
+                muted: Color::Rgb(210, 210, 210),
+                accent: Color::Rgb(255, 255, 0),
+                bar_bg: Color::Rgb(0, 0, 0),
+                selection_bg: Color::Rgb(90, 90, 0),
+                good: Color::Rgb(0, 255, 0),
+                warn: Color::Rgb(255, 140, 0),
+                info: Color::Rgb(0, 255, 255),
+                mono: false,
+            },
+        ),
+    ]
+}
+
+pub fn theme_kind_name(kind: ThemeKind) -> &'static str {
+    match kind {
+        ThemeKind::Dark => "dark",
+        ThemeKind::Light => "light",
+        ThemeKind::HighContrast => "high-contrast",
+    }
+}
+
+/// 按名字猜配色深浅，用来给 syntect 选语法高亮主题；自定义主题一律按
+/// 深色处理，和 `dark` 共用同一套语法配色。
+pub fn theme_kind_from_name(name: &str) -> ThemeKind {
+    match name {
+        "light" => ThemeKind::Light,
+        "high-contrast" => ThemeKind::HighContrast,
+        _ => ThemeKind::Dark,
+    }
+}
+
+fn find_themes_dir() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    for anc in cwd.ancestors() {
+        let p = anc.join("errorTK/tui/themes");
+        if p.is_dir() {
+            return Some(p);
+        }
+    }
+    None
+}
+
+/// 内置三套主题 + `errorTK/tui/themes/*.toml` 里找到的用户主题，
+/// 后者按文件名/`name` 字段覆盖同名内置主题，新名字则追加到末尾。
+pub fn load_theme_registry() -> Vec<(String, Theme)> {
+    let mut registry = builtin_themes();
+    let Some(dir) = find_themes_dir() else {
+        return registry;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return registry;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(spec) = toml::from_str::<ThemeSpec>(&content) else {
+            continue;
+        };
+        let name = spec.name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("custom")
+                .to_string()
+        });
+        let base_name = spec.extends.clone().unwrap_or_else(|| "dark".to_string());
+        let base = registry
+            .iter()
+            .find(|(n, _)| *n == base_name)
+            .map(|(_, t)| *t)
+            .unwrap_or(registry[0].1);
+        let theme = spec.merge_onto(base);
+        match registry.iter_mut().find(|(n, _)| *n == name) {
+            Some(slot) => slot.1 = theme,
+            None => registry.push((name, theme)),
+        }
+    }
+    registry
+}
+
+/// 遵循 https://no-color.org/：只要设了 `NO_COLOR`（哪怕是空字符串）或者
+/// 传入了 `--no-color`，就认为应该进入无色模式。在启动时判断一次，
+/// 避免每个渲染调用点各自重复读环境变量。
+pub fn no_color_requested(cli_flag: bool) -> bool {
+    cli_flag || std::env::var_os("NO_COLOR").is_some()
+}
+
+/// `mono` 为真时把所有颜色收敛成终端默认色，并置位 `Theme::mono` ——
+/// 渲染侧据此把选区/光标改用 `Modifier` 表达，不再依赖颜色填充。
+pub fn apply_no_color(theme: Theme, mono: bool) -> Theme {
+    if !mono {
+        return theme;
+    }
+    Theme {
+        fg: Color::Reset,
+        muted: Color::Reset,
+        accent: Color::Reset,
+        bar_bg: Color::Reset,
+        selection_bg: Color::Reset,
+        good: Color::Reset,
+        warn: Color::Reset,
+        info: Color::Reset,
+        mono: true,
+    }
+}